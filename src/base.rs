@@ -1,7 +1,8 @@
 //! RISC-V SBI Base extension test suite.
 
+use crate::binary::sbi_call_0;
 use sbi::{ExtensionInfo, Version};
-use sbi_spec::base::impl_id;
+use sbi_spec::base::{impl_id, EID_BASE, GET_SBI_SPEC_VERSION};
 
 /// Base extension test cases.
 #[derive(Clone, Debug)]
@@ -12,10 +13,14 @@ pub enum Case {
     Begin,
     /// Test process for getting SBI specification version.
     GetSbiSpecVersion(Version),
+    /// Test failed for reserved bit 31 of SBI specification version is not zero, with the raw value.
+    SpecVersionReservedBit(usize),
+    /// Test process for SBI specification version is not a known release, which may be a newer one.
+    UnknownSpecVersion(Version),
     /// Test process for getting SBI implementation ID.
     GetSbiImplId(Result<&'static str, usize>),
     /// Test process for getting version of SBI implementation.
-    GetSbiImplVersion(usize),
+    GetSbiImplVersion(ImplVersion),
     /// Test process for probe standard SBI extensions.
     ProbeExtensions(Extensions),
     /// Test process for getting vendor ID from RISC-V environment.
@@ -24,10 +29,75 @@ pub enum Case {
     GetMArchId(usize),
     /// Test process for getting implementation ID from RISC-V environment.
     GetMimpId(usize),
+    /// Test process for an extension found in experimental or vendor extension space,
+    /// with the value returned by probing.
+    VendorExtension(usize, usize),
     /// All test cases on base module finished.
     Pass,
 }
 
+/// Version of SBI implementation, decoded by the scheme of the implementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImplVersion {
+    /// OpenSBI version, encoded as `major << 16 | minor`.
+    OpenSbi {
+        /// The major version.
+        major: usize,
+        /// The minor version.
+        minor: usize,
+    },
+    /// RustSBI version, encoded as `major << 16 | minor << 8 | patch`.
+    RustSbi {
+        /// The major version.
+        major: usize,
+        /// The minor version.
+        minor: usize,
+        /// The patch version.
+        patch: usize,
+    },
+    /// Raw version of an implementation with unknown encoding.
+    Raw(usize),
+}
+
+impl ImplVersion {
+    /// Decodes raw implementation version by implementation ID.
+    #[inline]
+    pub const fn from_raw(impl_id: usize, raw: usize) -> Self {
+        match impl_id {
+            impl_id::OPEN_SBI => Self::OpenSbi {
+                major: raw >> 16,
+                minor: raw & 0xffff,
+            },
+            impl_id::RUST_SBI => Self::RustSbi {
+                major: raw >> 16,
+                minor: (raw >> 8) & 0xff,
+                patch: raw & 0xff,
+            },
+            _ => Self::Raw(raw),
+        }
+    }
+}
+
+impl core::fmt::Display for ImplVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OpenSbi { major, minor } => write!(f, "{major}.{minor}"),
+            Self::RustSbi {
+                major,
+                minor,
+                patch,
+            } => write!(f, "{major}.{minor}.{patch}"),
+            Self::Raw(raw) => write!(f, "{raw:#x}"),
+        }
+    }
+}
+
+/// 已发布的规范版本。
+const SPEC_RELEASES: [(usize, usize); 6] = [(0, 1), (0, 2), (0, 3), (1, 0), (2, 0), (3, 0)];
+
+/// 规范版本的保留位。
+const SPEC_VERSION_RESERVED: usize = 1 << 31;
+
 /// Information about all SBI standard extensions.
 #[derive(Clone, Debug)]
 pub struct Extensions {
@@ -43,44 +113,158 @@ pub struct Extensions {
     pub srst: ExtensionInfo,
     /// Performance Monitor Unit extension.
     pub pmu: ExtensionInfo,
+    /// Debug Console extension.
+    pub dbcn: ExtensionInfo,
+    /// System Suspend extension.
+    pub susp: ExtensionInfo,
+    /// Collaborative Processor Performance Control extension.
+    pub cppc: ExtensionInfo,
+    /// Nested Acceleration extension.
+    pub nacl: ExtensionInfo,
+    /// Steal-time Accounting extension.
+    pub sta: ExtensionInfo,
+    /// Supervisor Software Events extension.
+    pub sse: ExtensionInfo,
+    /// Firmware Features extension.
+    pub fwft: ExtensionInfo,
+    /// Debug Triggers extension.
+    pub dbtr: ExtensionInfo,
+    /// Message Proxy extension.
+    pub mpxy: ExtensionInfo,
+    /// Legacy extensions, indexed by extension ID.
+    pub legacy: [ExtensionInfo; 9],
+}
+
+/// 旧版扩展的名称，按扩展号排列。
+const LEGACY_NAMES: [&str; 9] = [
+    "LEGACY_SET_TIMER",
+    "LEGACY_CONSOLE_PUTCHAR",
+    "LEGACY_CONSOLE_GETCHAR",
+    "LEGACY_CLEAR_IPI",
+    "LEGACY_SEND_IPI",
+    "LEGACY_REMOTE_FENCE_I",
+    "LEGACY_REMOTE_SFENCE_VMA",
+    "LEGACY_REMOTE_SFENCE_VMA_ASID",
+    "LEGACY_SHUTDOWN",
+];
+
+impl Extensions {
+    /// Probe all SBI standard extensions.
+    pub fn probe() -> Self {
+        Self {
+            time: sbi::probe_extension(sbi::Timer),
+            spi: sbi::probe_extension(sbi::Ipi),
+            rfnc: sbi::probe_extension(sbi::Fence),
+            hsm: sbi::probe_extension(sbi::Hsm),
+            srst: sbi::probe_extension(sbi::Reset),
+            pmu: sbi::probe_extension(sbi::Pmu),
+            dbcn: sbi::probe_extension(sbi_spec::dbcn::EID_DBCN),
+            // sbi-rt 中 `Suspend` 的扩展号有误，直接使用扩展号探测
+            susp: sbi::probe_extension(sbi_spec::susp::EID_SUSP),
+            cppc: sbi::probe_extension(sbi_spec::cppc::EID_CPPC),
+            nacl: sbi::probe_extension(sbi_spec::nacl::EID_NACL),
+            sta: sbi::probe_extension(sbi_spec::sta::EID_STA),
+            sse: sbi::probe_extension(crate::sse::EID_SSE),
+            fwft: sbi::probe_extension(crate::fwft::EID_FWFT),
+            dbtr: sbi::probe_extension(crate::dbtr::EID_DBTR),
+            mpxy: sbi::probe_extension(crate::mpxy::EID_MPXY),
+            legacy: crate::legacy::LEGACY_EXTENSIONS.map(sbi::probe_extension),
+        }
+    }
+
+    /// Iterate over name, extension ID and probe result of all extensions,
+    /// standard extensions first and then legacy extensions.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize, ExtensionInfo)> + '_ {
+        let standard = [
+            ("TIME", sbi_spec::time::EID_TIME, self.time),
+            ("sPI", sbi_spec::spi::EID_SPI, self.spi),
+            ("RFNC", sbi_spec::rfnc::EID_RFNC, self.rfnc),
+            ("HSM", sbi_spec::hsm::EID_HSM, self.hsm),
+            ("SRST", sbi_spec::srst::EID_SRST, self.srst),
+            ("PMU", sbi_spec::pmu::EID_PMU, self.pmu),
+            ("DBCN", sbi_spec::dbcn::EID_DBCN, self.dbcn),
+            ("SUSP", sbi_spec::susp::EID_SUSP, self.susp),
+            ("CPPC", sbi_spec::cppc::EID_CPPC, self.cppc),
+            ("NACL", sbi_spec::nacl::EID_NACL, self.nacl),
+            ("STA", sbi_spec::sta::EID_STA, self.sta),
+            ("SSE", crate::sse::EID_SSE, self.sse),
+            ("FWFT", crate::fwft::EID_FWFT, self.fwft),
+            ("DBTR", crate::dbtr::EID_DBTR, self.dbtr),
+            ("MPXY", crate::mpxy::EID_MPXY, self.mpxy),
+        ];
+        let legacy = LEGACY_NAMES
+            .into_iter()
+            .zip(crate::legacy::LEGACY_EXTENSIONS)
+            .zip(self.legacy)
+            .map(|((name, eid), info)| (name, eid, info));
+        standard.into_iter().chain(legacy)
+    }
 }
 
 impl core::fmt::Display for Extensions {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "[Base")?;
-        if self.time.is_available() {
-            write!(f, ", TIME")?;
-        }
-        if self.spi.is_available() {
-            write!(f, ", sPI")?;
-        }
-        if self.rfnc.is_available() {
-            write!(f, ", RFNC")?;
-        }
-        if self.hsm.is_available() {
-            write!(f, ", HSM")?;
-        }
-        if self.srst.is_available() {
-            write!(f, ", SRST")?;
+        let legacy = crate::legacy::LEGACY_EXTENSIONS;
+        for (name, eid, info) in self.iter() {
+            if !legacy.contains(&eid) && info.is_available() {
+                write!(f, ", {name}")?;
+            }
         }
-        if self.pmu.is_available() {
-            write!(f, ", PMU")?;
+        if self.legacy.iter().any(ExtensionInfo::is_available) {
+            write!(f, ", Legacy")?;
         }
         write!(f, "]")
     }
 }
 
+/// Sweeping mode over experimental and vendor extension space.
+#[derive(Clone, Copy, Debug)]
+pub enum ExtensionSweep {
+    /// Do not probe experimental or vendor extensions.
+    Disabled,
+    /// Probe every `step`-th extension ID from the start of each space.
+    Sampled(usize),
+    /// Probe every extension ID in both spaces.
+    Exhaustive,
+}
+
+/// 实验性扩展空间。
+const EXPERIMENTAL_EXTENSIONS: core::ops::RangeInclusive<usize> = 0x0800_0000..=0x08ff_ffff;
+/// 厂商扩展空间。
+const VENDOR_EXTENSIONS: core::ops::RangeInclusive<usize> = 0x0900_0000..=0x09ff_ffff;
+
 /// Test base extension.
 ///
 /// The test case output would be handled in `f`.
-pub fn test(mut f: impl FnMut(Case)) {
+#[inline]
+pub fn test(f: impl FnMut(Case)) {
+    test_with_sweep(ExtensionSweep::Disabled, f)
+}
+
+/// Test base extension, probing experimental and vendor extension space as `sweep` chooses.
+///
+/// The test case output would be handled in `f`.
+pub fn test_with_sweep(sweep: ExtensionSweep, mut f: impl FnMut(Case)) {
     if sbi::probe_extension(sbi::Base).is_unavailable() {
         f(Case::NotExist);
         return;
     }
     f(Case::Begin);
-    f(Case::GetSbiSpecVersion(sbi::get_spec_version()));
-    f(Case::GetSbiImplId(match sbi::get_sbi_impl_id() {
+    let mut pass = true;
+    // `Version` 会丢弃保留位，直接调用以取得原始值
+    let raw = sbi_call_0(EID_BASE, GET_SBI_SPEC_VERSION).value;
+    let version = Version::from_raw(raw);
+    f(Case::GetSbiSpecVersion(version));
+    if raw & SPEC_VERSION_RESERVED != 0 {
+        f(Case::SpecVersionReservedBit(raw));
+        pass = false;
+    }
+    // 未知版本可能是更新的规范，只报告而不视为失败
+    if !SPEC_RELEASES.contains(&(version.major(), version.minor())) {
+        f(Case::UnknownSpecVersion(version));
+    }
+    let sbi_impl_id = sbi::get_sbi_impl_id();
+    f(Case::GetSbiImplId(match sbi_impl_id {
         impl_id::BBL => Ok("BBL"),
         impl_id::OPEN_SBI => Ok("OpenSBI"),
         impl_id::XVISOR => Ok("Xvisor"),
@@ -90,17 +274,31 @@ pub fn test(mut f: impl FnMut(Case)) {
         impl_id::COFFER => Ok("Coffer"),
         unknown => Err(unknown),
     }));
-    f(Case::GetSbiImplVersion(sbi::get_sbi_impl_version()));
-    f(Case::ProbeExtensions(Extensions {
-        time: sbi::probe_extension(sbi::Timer),
-        spi: sbi::probe_extension(sbi::Ipi),
-        rfnc: sbi::probe_extension(sbi::Fence),
-        hsm: sbi::probe_extension(sbi::Hsm),
-        srst: sbi::probe_extension(sbi::Reset),
-        pmu: sbi::probe_extension(sbi::Pmu),
-    }));
+    f(Case::GetSbiImplVersion(ImplVersion::from_raw(
+        sbi_impl_id,
+        sbi::get_sbi_impl_version(),
+    )));
+    f(Case::ProbeExtensions(Extensions::probe()));
     f(Case::GetMVendorId(sbi::get_mvendorid()));
     f(Case::GetMArchId(sbi::get_marchid()));
     f(Case::GetMimpId(sbi::get_mimpid()));
-    f(Case::Pass);
+    let step = match sweep {
+        ExtensionSweep::Disabled => None,
+        ExtensionSweep::Sampled(step) => Some(step.max(1)),
+        ExtensionSweep::Exhaustive => Some(1),
+    };
+    if let Some(step) = step {
+        for eid in EXPERIMENTAL_EXTENSIONS
+            .step_by(step)
+            .chain(VENDOR_EXTENSIONS.step_by(step))
+        {
+            let info = sbi::probe_extension(eid);
+            if info.is_available() {
+                f(Case::VendorExtension(eid, info.raw));
+            }
+        }
+    }
+    if pass {
+        f(Case::Pass);
+    }
 }
@@ -0,0 +1,195 @@
+//! 分批启动副核，供需要在副核上测试的模块使用。
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use sbi::SbiRet;
+use sbi_spec::{binary::HartMask, hsm::hart_state};
+
+/// 每批测试的核数。
+pub(crate) const BATCH_SIZE: usize = 4;
+
+static mut STACK: [ItemPerHart; BATCH_SIZE] = [ItemPerHart::ZERO; BATCH_SIZE];
+
+/// 每个副核的同步状态和栈。
+#[repr(C, align(512))]
+pub(crate) struct ItemPerHart {
+    pub stage: AtomicU32,
+    signal: AtomicU32,
+    /// 副核进入的 rust 函数。
+    main: usize,
+    stack: [u8; 496],
+}
+
+/// 副核的 rust 入口，参数为核号和本核的 [`ItemPerHart`]。
+pub(crate) type HartMain = extern "C" fn(usize, *mut ItemPerHart) -> !;
+
+pub(crate) const STAGE_IDLE: u32 = 0;
+
+impl ItemPerHart {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: Self = Self {
+        stage: AtomicU32::new(STAGE_IDLE),
+        signal: AtomicU32::new(0),
+        main: 0,
+        stack: [0; 496],
+    };
+
+    #[inline]
+    fn reset(&mut self, main: HartMain) -> *const ItemPerHart {
+        self.stage.store(STAGE_IDLE, Ordering::Relaxed);
+        self.signal.store(0, Ordering::Relaxed);
+        self.main = main as _;
+        self as _
+    }
+
+    #[inline]
+    pub fn wait_stage(&self, stage: u32) {
+        while self.stage.load(Ordering::Acquire) != stage {
+            core::hint::spin_loop();
+        }
+    }
+
+    #[inline]
+    pub fn send_signal(&self) {
+        self.signal.store(1, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn wait_signal(&self) {
+        while self
+            .signal
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// 批次中第 `i` 个核的同步状态。
+#[inline]
+pub(crate) fn item(i: usize) -> &'static ItemPerHart {
+    unsafe { &(*core::ptr::addr_of!(STACK))[i] }
+}
+
+/// 以批次中第 `i` 个核的栈启动 `hartid`，进入 `main`。
+#[inline]
+pub(crate) fn start(hartid: usize, i: usize, main: HartMain) -> SbiRet {
+    let ptr = unsafe { (*core::ptr::addr_of_mut!(STACK))[i].reset(main) };
+    sbi::hart_start(hartid, entry as _, ptr as _)
+}
+
+/// 不可恢复休眠后，以 `opaque` 对应的栈重新进入 `main`。
+#[inline]
+pub(crate) fn suspend_nonretentive(opaque: *mut ItemPerHart) -> SbiRet {
+    sbi::hart_suspend(sbi::NonRetentive, entry as _, opaque as _)
+}
+
+/// 可恢复休眠。
+#[inline]
+pub(crate) fn suspend_retentive(opaque: *mut ItemPerHart) -> SbiRet {
+    sbi::hart_suspend(sbi::Retentive, entry as _, opaque as _)
+}
+
+/// 一批核的核掩码。
+#[inline]
+pub(crate) fn hart_mask(batch: &[usize]) -> HartMask {
+    let mut mask = 1usize;
+    for hartid in &batch[1..] {
+        mask |= 1 << (hartid - batch[0]);
+    }
+    HartMask::from_mask_base(mask, batch[0])
+}
+
+/// 分批过程中的事件。
+pub(crate) enum Event<'a> {
+    /// 副核在测试前不处于停止状态。
+    StartedBeforeTest(usize),
+    /// 收集到一批停止的副核，返回是否通过测试。
+    Batch(&'a [usize]),
+}
+
+/// 分批测试的结果。
+pub(crate) enum Batches {
+    /// 所有批次通过测试。
+    Passed,
+    /// 某一批次未通过测试。
+    Failed,
+    /// 没有找到能参与测试的副核。
+    NoStoppedHart,
+}
+
+/// 将 `hart_mask` 中除主核外处于停止状态的核分批，逐批交给 `event` 测试。
+pub(crate) fn for_each_batch(
+    primary_hart_id: usize,
+    mut hart_mask: usize,
+    hart_mask_base: usize,
+    mut event: impl FnMut(Event) -> bool,
+) -> Batches {
+    const STOPPED: SbiRet = SbiRet::success(hart_state::STOPPED);
+
+    let mut batch = [0usize; BATCH_SIZE];
+    let mut batch_count = 0;
+    let mut batch_size = 0;
+    let mut hartid = hart_mask_base;
+    while hart_mask != 0 {
+        if hartid != primary_hart_id {
+            // 副核在测试前必须处于停止状态
+            if sbi::hart_get_status(hartid) == STOPPED {
+                batch[batch_size] = hartid;
+                batch_size += 1;
+                // 收集一个批次，执行测试
+                if batch_size == BATCH_SIZE {
+                    if event(Event::Batch(&batch)) {
+                        batch_count += 1;
+                        batch_size = 0;
+                    } else {
+                        return Batches::Failed;
+                    }
+                }
+            }
+            // 副核不在停止状态
+            else {
+                event(Event::StartedBeforeTest(hartid));
+            }
+        }
+        let distance = hart_mask.trailing_zeros() + 1;
+        hart_mask >>= distance;
+        hartid += distance as usize;
+    }
+    // 为不满一批次的核执行测试
+    if batch_size > 0 {
+        if event(Event::Batch(&batch[..batch_size])) {
+            Batches::Passed
+        } else {
+            Batches::Failed
+        }
+    } else if batch_count > 0 {
+        Batches::Passed
+    } else {
+        Batches::NoStoppedHart
+    }
+}
+
+/// 副核启动入口
+#[naked]
+unsafe extern "C" fn entry(hartid: usize, opaque: *mut ItemPerHart) -> ! {
+    core::arch::asm!(
+        "csrw sie, zero",   // 关中断
+        "call {set_stack}", // 设置栈
+        "j    {rust_main}", // 进入 rust
+        set_stack = sym set_stack,
+        rust_main = sym rust_main,
+        options(noreturn),
+    )
+}
+
+#[naked]
+unsafe extern "C" fn set_stack(hart_id: usize, ptr: *const ItemPerHart) {
+    core::arch::asm!("addi sp, a1, 512", "ret", options(noreturn));
+}
+
+#[inline(never)]
+extern "C" fn rust_main(hart_id: usize, opaque: *mut ItemPerHart) -> ! {
+    let main: HartMain = unsafe { core::mem::transmute((*opaque).main) };
+    main(hart_id, opaque)
+}
@@ -0,0 +1,188 @@
+//! 直接发起 SBI 调用，用于 `sbi-rt` 尚未封装的函数。
+
+use sbi::SbiRet;
+
+#[inline(always)]
+pub(crate) fn sbi_call_0(eid: usize, fid: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") eid,
+            in("a6") fid,
+            lateout("a0") error,
+            lateout("a1") value,
+        );
+    }
+    SbiRet { error, value }
+}
+
+#[inline(always)]
+pub(crate) fn sbi_call_1(eid: usize, fid: usize, arg0: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") eid,
+            in("a6") fid,
+            inlateout("a0") arg0 => error,
+            lateout("a1") value,
+        );
+    }
+    SbiRet { error, value }
+}
+
+#[inline(always)]
+pub(crate) fn sbi_call_2(eid: usize, fid: usize, arg0: usize, arg1: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") eid,
+            in("a6") fid,
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+        );
+    }
+    SbiRet { error, value }
+}
+
+#[inline(always)]
+pub(crate) fn sbi_call_3(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") eid,
+            in("a6") fid,
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+            in("a2") arg2,
+        );
+    }
+    SbiRet { error, value }
+}
+
+#[inline(always)]
+pub(crate) fn sbi_call_5(
+    eid: usize,
+    fid: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") eid,
+            in("a6") fid,
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+        );
+    }
+    SbiRet { error, value }
+}
+
+/// 一次 SBI 调用的扩展号、函数号和参数。
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Call {
+    pub eid: usize,
+    pub fid: usize,
+    pub args: [usize; 6],
+}
+
+impl Call {
+    /// 发起此调用。
+    #[inline]
+    pub fn invoke(self) -> SbiRet {
+        let [arg0, arg1, arg2, arg3, arg4, arg5] = self.args;
+        let (error, value);
+        unsafe {
+            core::arch::asm!(
+                "ecall",
+                in("a7") self.eid,
+                in("a6") self.fid,
+                inlateout("a0") arg0 => error,
+                inlateout("a1") arg1 => value,
+                in("a2") arg2,
+                in("a3") arg3,
+                in("a4") arg4,
+                in("a5") arg5,
+            );
+        }
+        SbiRet { error, value }
+    }
+}
+
+/// 可用扩展中没有可见副作用的调用，用于检查调用前后应保持不变的状态。
+///
+/// 包含一个不支持的调用，以覆盖错误返回的路径。
+pub(crate) fn harmless_calls(hartid: usize) -> impl Iterator<Item = Call> {
+    use sbi_spec::{base::EID_BASE, *};
+
+    const fn call(eid: usize, fid: usize, args: [usize; 6]) -> Call {
+        Call { eid, fid, args }
+    }
+    [
+        call(EID_BASE, base::GET_SBI_SPEC_VERSION, [0; 6]),
+        call(EID_BASE, base::GET_SBI_IMPL_ID, [0; 6]),
+        call(EID_BASE, base::GET_SBI_IMPL_VERSION, [0; 6]),
+        call(EID_BASE, base::PROBE_EXTENSION, [EID_BASE, 0, 0, 0, 0, 0]),
+        call(EID_BASE, base::GET_MVENDORID, [0; 6]),
+        call(EID_BASE, base::GET_MARCHID, [0; 6]),
+        call(EID_BASE, base::GET_MIMPID, [0; 6]),
+        call(EID_BASE, 0x7fff_ffff, [0; 6]),
+        // 最远的定时器，同时清除挂起的定时器中断；RV32 上时间的高位在 `a1`
+        call(
+            time::EID_TIME,
+            time::SET_TIMER,
+            [usize::MAX, usize::MAX, 0, 0, 0, 0],
+        ),
+        // 空的核掩码不指向任何核
+        call(spi::EID_SPI, spi::SEND_IPI, [0; 6]),
+        call(rfnc::EID_RFNC, rfnc::REMOTE_FENCE_I, [0; 6]),
+        call(hsm::EID_HSM, hsm::HART_GET_STATUS, [hartid, 0, 0, 0, 0, 0]),
+        // 保留的复位类型，应返回参数错误
+        call(
+            srst::EID_SRST,
+            srst::SYSTEM_RESET,
+            [0x0000_0003, 0, 0, 0, 0, 0],
+        ),
+        call(pmu::EID_PMU, pmu::NUM_COUNTERS, [0; 6]),
+        // 回车不会打乱测试输出
+        call(
+            dbcn::EID_DBCN,
+            dbcn::CONSOLE_WRITE,
+            [1, b"\r".as_ptr() as _, 0, 0, 0, 0],
+        ),
+        // 保留的休眠类型，应返回参数错误
+        call(susp::EID_SUSP, susp::SUSPEND, [0x0000_0001, 0, 0, 0, 0, 0]),
+        call(cppc::EID_CPPC, cppc::PROBE, [0; 6]),
+        call(nacl::EID_NACL, nacl::PROBE_FEATURE, [0; 6]),
+        // 关闭偷取时间共享内存
+        call(
+            sta::EID_STA,
+            sta::SET_SHMEM,
+            [usize::MAX, usize::MAX, 0, 0, 0, 0],
+        ),
+        // 读取零个属性，应返回参数错误
+        call(crate::sse::EID_SSE, 0, [0xffff_0000, 0, 0, 0, 0, 0]),
+        call(crate::fwft::EID_FWFT, 1, [0; 6]),
+        call(crate::dbtr::EID_DBTR, 0, [0; 6]),
+        call(crate::mpxy::EID_MPXY, 0, [0; 6]),
+        call(legacy::LEGACY_CLEAR_IPI, 0, [0; 6]),
+        call(
+            legacy::LEGACY_CONSOLE_PUTCHAR,
+            0,
+            [b'\r' as _, 0, 0, 0, 0, 0],
+        ),
+    ]
+    .into_iter()
+    .filter(|call| call.eid == EID_BASE || sbi::probe_extension(call.eid).is_available())
+}
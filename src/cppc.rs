@@ -0,0 +1,221 @@
+//! Collaborative processor performance control extension test suite.
+
+use sbi::SbiRet;
+use sbi_spec::{binary::Error, cppc::EID_CPPC};
+
+/// Collaborative processor performance control extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for CPPC extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for probing a CPPC register.
+    ///
+    /// The `width` is zero if the register is not implemented.
+    Probe {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// Width of the register in bits.
+        width: usize,
+    },
+    /// Test failed for can't probe a CPPC register with [`SbiRet`] error.
+    ProbeFailed {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for a reserved CPPC register ID is not rejected with `SBI_ERR_INVALID_PARAM`.
+    ReservedNotRejected {
+        /// The reserved CPPC register ID.
+        reg_id: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for reading a CPPC register.
+    Read {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// Value of the register.
+        value: u64,
+    },
+    /// Test failed for can't read a CPPC register with [`SbiRet`] error.
+    ReadFailed {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for writing a read-only CPPC register is not rejected with `SBI_ERR_DENIED`.
+    ReadOnlyWriteNotDenied {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for desired performance register has been written within advertised range.
+    WriteDesired {
+        /// Value written to desired performance register.
+        value: u64,
+        /// Value of lowest performance register.
+        lowest: u64,
+        /// Value of highest performance register.
+        highest: u64,
+    },
+    /// Test failed for can't write a CPPC register with [`SbiRet`] error.
+    WriteFailed {
+        /// The CPPC register ID.
+        reg_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// All test cases on CPPC extension has passed.
+    Pass,
+}
+
+const HIGHEST_PERFORMANCE: u32 = 0x00;
+const NOMINAL_PERFORMANCE: u32 = 0x01;
+const LOWEST_PERFORMANCE: u32 = 0x03;
+const DESIRED_PERFORMANCE: u32 = 0x05;
+
+/// 所有已定义的 CPPC 寄存器编号，以及是否只读。
+const REGISTERS: [(u32, bool); 22] = [
+    (HIGHEST_PERFORMANCE, true),  // HighestPerformance
+    (NOMINAL_PERFORMANCE, true),  // NominalPerformance
+    (0x02, true),                 // LowestNonlinearPerformance
+    (LOWEST_PERFORMANCE, true),   // LowestPerformance
+    (0x04, true),                 // GuaranteedPerformanceRegister
+    (DESIRED_PERFORMANCE, false), // DesiredPerformanceRegister
+    (0x06, false),                // MinimumPerformanceRegister
+    (0x07, false),                // MaximumPerformanceRegister
+    (0x08, false),                // PerformanceReductionToleranceRegister
+    (0x09, false),                // TimeWindowRegister
+    (0x0a, true),                 // CounterWraparoundTime
+    (0x0b, true),                 // ReferencePerformanceCounterRegister
+    (0x0c, true),                 // DeliveredPerformanceCounterRegister
+    (0x0d, false),                // PerformanceLimitedRegister
+    (0x0e, false),                // CPPCEnableRegister
+    (0x0f, false),                // AutonomousSelectionEnable
+    (0x10, false),                // AutonomousActivityWindowRegister
+    (0x11, false),                // EnergyPerformancePreferenceRegister
+    (0x12, true),                 // ReferencePerformance
+    (0x13, true),                 // LowestFrequency
+    (0x14, true),                 // NominalFrequency
+    (0x8000_0000, true),          // TransitionLatency
+];
+
+/// 保留的 CPPC 寄存器编号。
+const RESERVED_REGISTERS: [u32; 2] = [0x15, 0x8000_0001];
+
+/// Test CPPC extension.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_CPPC).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    for reg_id in RESERVED_REGISTERS {
+        let ret = sbi::cppc_probe(reg_id);
+        if ret.err() != Some(Error::InvalidParam) {
+            f(Case::ReservedNotRejected { reg_id, ret });
+            return;
+        }
+    }
+    let mut pass = true;
+    for (reg_id, read_only) in REGISTERS {
+        let ret = sbi::cppc_probe(reg_id);
+        let Some(width) = ret.ok() else {
+            f(Case::ProbeFailed { reg_id, ret });
+            pass = false;
+            continue;
+        };
+        f(Case::Probe { reg_id, width });
+        if width == 0 {
+            continue;
+        }
+        let value = match read(reg_id) {
+            Ok(value) => value,
+            Err(ret) => {
+                f(Case::ReadFailed { reg_id, ret });
+                pass = false;
+                continue;
+            }
+        };
+        f(Case::Read { reg_id, value });
+        // 以读出的值写回只读寄存器，即使未被拒绝也不会改变性能状态
+        if read_only {
+            let ret = sbi::cppc_write(reg_id, value);
+            if ret.err() != Some(Error::Denied) {
+                f(Case::ReadOnlyWriteNotDenied { reg_id, ret });
+                pass = false;
+            }
+        }
+    }
+    if pass && test_write_desired(&mut f) {
+        f(Case::Pass);
+    }
+}
+
+/// 读取 CPPC 寄存器，在 RV32 上合并高 32 位。
+fn read(reg_id: u32) -> Result<u64, SbiRet> {
+    let ret = sbi::cppc_read(reg_id);
+    let lo = ret.ok().ok_or(ret)? as u64;
+    match () {
+        #[cfg(target_pointer_width = "32")]
+        () => {
+            let ret = sbi::cppc_read_hi(reg_id);
+            let hi = ret.ok().ok_or(ret)? as u64;
+            Ok(hi << 32 | lo)
+        }
+        #[cfg(not(target_pointer_width = "32"))]
+        () => Ok(lo),
+    }
+}
+
+/// 在最低性能和最高性能之间写入期望性能寄存器，然后恢复原值。
+fn test_write_desired(mut f: impl FnMut(Case)) -> bool {
+    let implemented = |reg_id| sbi::cppc_probe(reg_id).ok().is_some_and(|width| width != 0);
+    if ![HIGHEST_PERFORMANCE, LOWEST_PERFORMANCE, DESIRED_PERFORMANCE]
+        .into_iter()
+        .all(implemented)
+    {
+        return true;
+    }
+    let (Ok(highest), Ok(lowest), Ok(original)) = (
+        read(HIGHEST_PERFORMANCE),
+        read(LOWEST_PERFORMANCE),
+        read(DESIRED_PERFORMANCE),
+    ) else {
+        return true;
+    };
+    // 优先写入标称性能，不在范围内则写入最低性能
+    let value = match read(NOMINAL_PERFORMANCE) {
+        Ok(nominal) if (lowest..=highest).contains(&nominal) => nominal,
+        _ => lowest,
+    };
+    let ret = sbi::cppc_write(DESIRED_PERFORMANCE, value);
+    if ret.is_err() {
+        f(Case::WriteFailed {
+            reg_id: DESIRED_PERFORMANCE,
+            ret,
+        });
+        return false;
+    }
+    f(Case::WriteDesired {
+        value,
+        lowest,
+        highest,
+    });
+    let ret = sbi::cppc_write(DESIRED_PERFORMANCE, original);
+    if ret.is_err() {
+        f(Case::WriteFailed {
+            reg_id: DESIRED_PERFORMANCE,
+            ret,
+        });
+        return false;
+    }
+    true
+}
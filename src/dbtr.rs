@@ -0,0 +1,230 @@
+//! Debug triggers extension test suite.
+
+use crate::{
+    binary::{sbi_call_1, sbi_call_2, sbi_call_3},
+    thread::Thread,
+};
+use riscv::register::scause::{self, Trap};
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// Debug triggers extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for debug triggers extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for number of triggers of a type.
+    ///
+    /// The `ttype` is zero for triggers of any type.
+    NumTriggers {
+        /// The trigger type in `tdata1`.
+        ttype: usize,
+        /// Number of triggers.
+        count: usize,
+    },
+    /// Execute trigger tests are skipped for no address match trigger is available.
+    NoAddressMatchTrigger,
+    /// Test failed for installing triggers without shared memory is not rejected with `SBI_ERR_NO_SHMEM`.
+    NoShmemNotRejected(SbiRet),
+    /// Test failed for can't set shared memory with [`SbiRet`] error.
+    SetShmemFailed(SbiRet),
+    /// Test failed for can't install the execute trigger with [`SbiRet`] error.
+    InstallFailed(SbiRet),
+    /// Test process for the execute trigger has been installed.
+    Installed {
+        /// Index of the installed trigger.
+        idx: usize,
+        /// The `tdata1` value of the installed trigger.
+        tdata1: usize,
+    },
+    /// Test failed for can't read the installed trigger with [`SbiRet`] error.
+    ReadFailed(SbiRet),
+    /// Test failed for the installed trigger does not match the target address.
+    AddressMismatch {
+        /// The target function address.
+        expected: usize,
+        /// The `tdata2` value read from the installed trigger.
+        actual: usize,
+    },
+    /// Test process for breakpoint trap has been caught at the target function.
+    Breakpoint,
+    /// Test failed for breakpoint trap is caught at another address.
+    BreakpointMismatch {
+        /// The target function address.
+        expected: usize,
+        /// The `sepc` value upon breakpoint trap.
+        sepc: usize,
+    },
+    /// Test failed for unexpected trap occurred upon running the target function.
+    UnexpectedTrap(Trap),
+    /// Test failed for can't uninstall the execute trigger with [`SbiRet`] error.
+    UninstallFailed(SbiRet),
+    /// Test failed for breakpoint trap is still caught after the trigger is uninstalled.
+    TriggeredAfterUninstall,
+    /// All test cases on debug triggers extension has passed.
+    Pass,
+}
+
+pub(crate) const EID_DBTR: usize = 0x44425452;
+const NUM_TRIGGERS: usize = 0;
+const SET_SHMEM: usize = 1;
+const READ_TRIGGERS: usize = 2;
+const INSTALL_TRIGGERS: usize = 3;
+const UNINSTALL_TRIGGERS: usize = 5;
+
+/// 地址匹配触发器 `mcontrol`。
+const TYPE_MCONTROL: usize = 2;
+/// 地址匹配触发器 `mcontrol6`。
+const TYPE_MCONTROL6: usize = 6;
+/// 需要查询数量的触发器类型：`mcontrol`、`icount`、`itrigger`、`etrigger` 和 `mcontrol6`。
+const TYPES: [usize; 5] = [TYPE_MCONTROL, 3, 4, 5, TYPE_MCONTROL6];
+
+/// `tdata1` 中在 S 态执行时匹配的位。
+const TDATA1_S_EXECUTE: usize = 1 << 4 | 1 << 2;
+
+/// 共享内存中的一项，依次为 `tstate` 或触发器序号、`tdata1`、`tdata2` 和 `tdata3`。
+#[repr(C, align(64))]
+struct TriggerEntry([usize; 4]);
+
+static mut SHMEM: TriggerEntry = TriggerEntry([0; 4]);
+
+/// Test debug triggers extension.
+///
+/// An execute trigger is installed on a target function, which is
+/// then run in a thread twice, before and after the trigger is uninstalled.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_DBTR).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    // 查询各类触发器的数量
+    for ttype in [0].into_iter().chain(TYPES) {
+        let count = sbi_call_1(EID_DBTR, NUM_TRIGGERS, tdata1_type(ttype)).value;
+        f(Case::NumTriggers { ttype, count });
+    }
+    let ttype = [TYPE_MCONTROL6, TYPE_MCONTROL]
+        .into_iter()
+        .find(|ttype| sbi_call_1(EID_DBTR, NUM_TRIGGERS, tdata1_type(*ttype)).value != 0);
+    let Some(ttype) = ttype else {
+        f(Case::NoAddressMatchTrigger);
+        f(Case::Pass);
+        return;
+    };
+    let entry = core::ptr::addr_of_mut!(SHMEM);
+    let tdata1 = tdata1_type(ttype) | TDATA1_S_EXECUTE;
+    let tdata2 = target as usize;
+    unsafe { (*entry).0 = [0, tdata1, tdata2, 0] };
+    // 未设置共享内存时不能安装触发器
+    let ret = set_shmem(usize::MAX, usize::MAX);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let ret = sbi_call_1(EID_DBTR, INSTALL_TRIGGERS, 1);
+    if ret.err() != Some(Error::NoShmem) {
+        f(Case::NoShmemNotRejected(ret));
+        return;
+    }
+    // 设置共享内存并安装触发器
+    let ret = set_shmem(entry as _, 0);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let ret = sbi_call_1(EID_DBTR, INSTALL_TRIGGERS, 1);
+    if ret.is_err() {
+        f(Case::InstallFailed(ret));
+        let _ = set_shmem(usize::MAX, usize::MAX);
+        return;
+    }
+    let idx = unsafe { (*entry).0[0] };
+    f(Case::Installed { idx, tdata1 });
+    let pass = test_installed(idx, entry, &mut f);
+    // 无论成败都卸载触发器
+    let ret = sbi_call_2(EID_DBTR, UNINSTALL_TRIGGERS, idx, 1);
+    let _ = set_shmem(usize::MAX, usize::MAX);
+    if !pass {
+        return;
+    }
+    if ret.is_err() {
+        f(Case::UninstallFailed(ret));
+        return;
+    }
+    // 卸载后不应再陷入断点
+    match run_target() {
+        (Trap::Exception(scause::Exception::IllegalInstruction), _) => f(Case::Pass),
+        (Trap::Exception(scause::Exception::Breakpoint), _) => f(Case::TriggeredAfterUninstall),
+        (trap, _) => f(Case::UnexpectedTrap(trap)),
+    }
+}
+
+/// 读回已安装的触发器，然后在线程中执行目标函数，检查断点陷入。
+fn test_installed(idx: usize, entry: *mut TriggerEntry, mut f: impl FnMut(Case)) -> bool {
+    use scause::Exception;
+
+    let ret = sbi_call_2(EID_DBTR, READ_TRIGGERS, idx, 1);
+    if ret.is_err() {
+        f(Case::ReadFailed(ret));
+        return false;
+    }
+    let actual = unsafe { (*entry).0[2] };
+    if actual != target as usize {
+        f(Case::AddressMismatch {
+            expected: target as _,
+            actual,
+        });
+        return false;
+    }
+    match run_target() {
+        (Trap::Exception(Exception::Breakpoint), sepc) if sepc == target as usize => {
+            f(Case::Breakpoint);
+            true
+        }
+        (Trap::Exception(Exception::Breakpoint), sepc) => {
+            f(Case::BreakpointMismatch {
+                expected: target as _,
+                sepc,
+            });
+            false
+        }
+        (trap, _) => {
+            f(Case::UnexpectedTrap(trap));
+            false
+        }
+    }
+}
+
+/// 在线程中执行目标函数，返回线程陷入的原因和 `sepc`。
+fn run_target() -> (Trap, usize) {
+    use riscv::register::sepc;
+
+    let mut thread = Thread::new(target as _);
+    unsafe { thread.execute() };
+    (scause::read().cause(), sepc::read())
+}
+
+/// 目标函数，未被断点打断时以非法指令陷入。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn target() {
+    core::arch::asm!("unimp", options(noreturn))
+}
+
+/// 只设置了类型的 `tdata1`。
+#[inline]
+const fn tdata1_type(ttype: usize) -> usize {
+    ttype << (usize::BITS - 4)
+}
+
+#[inline]
+fn set_shmem(lo: usize, hi: usize) -> SbiRet {
+    sbi_call_3(EID_DBTR, SET_SHMEM, lo, hi, 0)
+}
@@ -0,0 +1,625 @@
+//! Cross-extension test suite on preservation of floating-point and vector state.
+
+use crate::{
+    binary::{harmless_calls, Call},
+    thread::Thread,
+};
+use riscv::register::{
+    scause::{self, Exception, Trap},
+    sepc,
+};
+
+/// Floating-point and vector state preservation test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for neither floating-point nor vector unit exists.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for floating-point and vector units have been detected.
+    Units {
+        /// Whether floating-point unit exists.
+        float: bool,
+        /// Length of a vector register in bytes, or `None` if vector unit does not exist.
+        vlenb: Option<usize>,
+    },
+    /// Vector state test is skipped for vector registers are longer than supported by the test.
+    VectorTooLong(usize),
+    /// Test process for floating-point and vector state is preserved across an SBI call.
+    Preserved {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+    },
+    /// Test failed for the state field `FS` or `VS` in `sstatus` is changed by an SBI call.
+    StatusChanged {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// Name of the state field.
+        field: &'static str,
+        /// The state before the SBI call.
+        expected: usize,
+        /// The state after the SBI call.
+        actual: usize,
+    },
+    /// Test failed for a floating-point register is clobbered by an SBI call.
+    FloatClobbered {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// Number of the clobbered register.
+        register: usize,
+        /// The value before the SBI call.
+        expected: u64,
+        /// The value after the SBI call.
+        actual: u64,
+    },
+    /// Test failed for a vector register is clobbered by an SBI call.
+    VectorClobbered {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// Number of the clobbered register.
+        register: usize,
+    },
+    /// Test failed for a floating-point or vector CSR is clobbered by an SBI call.
+    CsrClobbered {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// Name of the clobbered CSR.
+        csr: &'static str,
+        /// The value before the SBI call.
+        expected: usize,
+        /// The value after the SBI call.
+        actual: usize,
+    },
+    /// All test cases on floating-point and vector state preservation has passed.
+    Pass,
+}
+
+/// `sstatus` 中扩展状态字段的位置。
+const FS_SHIFT: usize = 13;
+const VS_SHIFT: usize = 9;
+
+/// 扩展状态。
+const INITIAL: usize = 0b01;
+const CLEAN: usize = 0b10;
+
+/// 浮点寄存器填充值的高位，低位为寄存器编号，是一个非规范的 NaN。
+const FLOAT_PATTERN: u64 = 0x7ff5_a5a5_0000_0000;
+/// 只有单精度时的填充值，同样是一个非规范的 NaN。
+const SINGLE_PATTERN: u64 = 0x7fa5_a500;
+/// 舍入模式向上，并置起部分异常标志。
+const FCSR: usize = 0b011_01011;
+/// `vxrm` 为 2，并置起 `vxsat`。
+const VCSR: usize = 0b101;
+
+/// 支持的最大向量寄存器字节数。
+const MAX_VLENB: usize = 64;
+/// 向量寄存器文件的最大字节数。
+const VECTOR_FILE: usize = 32 * MAX_VLENB;
+
+/// 调用者的浮点寄存器、填充值和调用后的值。
+#[repr(C)]
+struct FloatFrame {
+    saved: [u64; 32],
+    expected: [u64; 32],
+    actual: [u64; 32],
+}
+
+/// 调用者的向量寄存器、填充值和调用后的值。
+#[repr(C, align(16))]
+struct VectorFrame {
+    saved: [u8; VECTOR_FILE],
+    expected: [u8; VECTOR_FILE],
+    actual: [u8; VECTOR_FILE],
+}
+
+static mut FLOAT: FloatFrame = FloatFrame {
+    saved: [0; 32],
+    expected: [0; 32],
+    actual: [0; 32],
+};
+
+static mut VECTOR: VectorFrame = VectorFrame {
+    saved: [0; VECTOR_FILE],
+    expected: [0; VECTOR_FILE],
+    actual: [0; VECTOR_FILE],
+};
+
+/// 向量 CSR。
+#[derive(Clone, Copy)]
+struct VectorCsrs {
+    vtype: usize,
+    vl: usize,
+    vcsr: usize,
+}
+
+/// 一次调用后的浮点状态。
+struct FloatState {
+    fs: usize,
+    fcsr: usize,
+}
+
+/// 一次调用后的向量状态。
+struct VectorState {
+    vs: usize,
+    csrs: VectorCsrs,
+}
+
+/// Test that SBI calls preserve floating-point and vector state.
+///
+/// Floating-point and vector units are detected by enabling them in `sstatus`
+/// and accessing their CSRs in a thread. For each side-effect free call,
+/// including `console_write` and `set_timer`, all floating-point registers,
+/// `fcsr`, and if present all vector registers, `vtype`, `vl` and `vcsr` are
+/// filled with distinctive values and state fields `FS` and `VS` are set to
+/// clean. Registers, CSRs and state fields are saved right after the call and
+/// compared, and the registers of the caller are restored. Current hart
+/// `hartid` is used as parameter of `HSM` status call.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hartid: usize, mut f: impl FnMut(Case)) {
+    let original_fs = status(FS_SHIFT);
+    let original_vs = status(VS_SHIFT);
+    // 打开单元并尝试访问
+    set_status(FS_SHIFT, INITIAL);
+    set_status(VS_SHIFT, INITIAL);
+    let float = probe(probe_float as _).is_some();
+    let double = float && probe(probe_double as _).is_some();
+    let vlenb = probe(probe_vector as _);
+    if !float && vlenb.is_none() {
+        set_status(FS_SHIFT, original_fs);
+        set_status(VS_SHIFT, original_vs);
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    f(Case::Units { float, vlenb });
+    let vlenb = match vlenb {
+        Some(vlenb) if vlenb > MAX_VLENB => {
+            f(Case::VectorTooLong(vlenb));
+            None
+        }
+        vlenb => vlenb,
+    };
+    // 准备填充值
+    let float_frame = core::ptr::addr_of_mut!(FLOAT);
+    let pattern = if double {
+        FLOAT_PATTERN
+    } else {
+        SINGLE_PATTERN
+    };
+    unsafe { (*float_frame).expected = core::array::from_fn(|n| pattern | n as u64) };
+    let vector_frame = core::ptr::addr_of_mut!(VECTOR);
+    let vector = vlenb.map(|vlenb| {
+        for (k, byte) in unsafe { (*vector_frame).expected.iter_mut() }.enumerate() {
+            *byte = vector_byte(k);
+        }
+        (vlenb, unsafe { vector_csrs() })
+    });
+    let mut pass = true;
+    for call in harmless_calls(hartid) {
+        // 浮点和向量分别发起一次调用，填充、调用和保存在同一段汇编中完成
+        let preserved = (!float || {
+            let state = unsafe { invoke_float(call, float_frame, double) };
+            check_float(call, state, unsafe { &*float_frame }, &mut f)
+        }) && match vector {
+            Some((vlenb, csrs)) => {
+                let state = unsafe { invoke_vector(call, vector_frame) };
+                check_vector(call, vlenb, csrs, state, unsafe { &*vector_frame }, &mut f)
+            }
+            None => true,
+        };
+        if !preserved {
+            pass = false;
+            break;
+        }
+        f(Case::Preserved {
+            eid: call.eid,
+            fid: call.fid,
+        });
+    }
+    set_status(FS_SHIFT, original_fs);
+    set_status(VS_SHIFT, original_vs);
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 检查状态字段、`fcsr` 和浮点寄存器。
+fn check_float(call: Call, state: FloatState, frame: &FloatFrame, mut f: impl FnMut(Case)) -> bool {
+    let Call { eid, fid, .. } = call;
+    if state.fs != CLEAN {
+        f(Case::StatusChanged {
+            eid,
+            fid,
+            field: "FS",
+            expected: CLEAN,
+            actual: state.fs,
+        });
+        return false;
+    }
+    if state.fcsr != FCSR {
+        f(Case::CsrClobbered {
+            eid,
+            fid,
+            csr: "fcsr",
+            expected: FCSR,
+            actual: state.fcsr,
+        });
+        return false;
+    }
+    for (register, (&expected, &actual)) in frame.expected.iter().zip(&frame.actual).enumerate() {
+        if actual != expected {
+            f(Case::FloatClobbered {
+                eid,
+                fid,
+                register,
+                expected,
+                actual,
+            });
+            return false;
+        }
+    }
+    true
+}
+
+/// 检查状态字段、向量 CSR 和向量寄存器。
+fn check_vector(
+    call: Call,
+    vlenb: usize,
+    csrs: VectorCsrs,
+    state: VectorState,
+    frame: &VectorFrame,
+    mut f: impl FnMut(Case),
+) -> bool {
+    let Call { eid, fid, .. } = call;
+    if state.vs != CLEAN {
+        f(Case::StatusChanged {
+            eid,
+            fid,
+            field: "VS",
+            expected: CLEAN,
+            actual: state.vs,
+        });
+        return false;
+    }
+    for (csr, expected, actual) in [
+        ("vtype", csrs.vtype, state.csrs.vtype),
+        ("vl", csrs.vl, state.csrs.vl),
+        ("vcsr", csrs.vcsr, state.csrs.vcsr),
+    ] {
+        if actual != expected {
+            f(Case::CsrClobbered {
+                eid,
+                fid,
+                csr,
+                expected,
+                actual,
+            });
+            return false;
+        }
+    }
+    for register in 0..32 {
+        if (register * vlenb..(register + 1) * vlenb).any(|k| frame.actual[k] != vector_byte(k)) {
+            f(Case::VectorClobbered { eid, fid, register });
+            return false;
+        }
+    }
+    true
+}
+
+/// 向量寄存器文件第 `k` 个字节的填充值。
+#[inline]
+fn vector_byte(k: usize) -> u8 {
+    (k as u8).wrapping_mul(0x3b) ^ (k >> 8) as u8
+}
+
+/// 读取 `sstatus` 中的状态字段。
+#[inline]
+fn status(shift: usize) -> usize {
+    let sstatus: usize;
+    unsafe { core::arch::asm!("csrr {}, sstatus", out(reg) sstatus) };
+    (sstatus >> shift) & 0b11
+}
+
+/// 修改 `sstatus` 中的状态字段。
+#[inline]
+fn set_status(shift: usize, value: usize) {
+    unsafe {
+        core::arch::asm!(
+            "   csrc sstatus, {mask}
+                csrs sstatus, {value}
+            ",
+            mask  = in(reg) 0b11 << shift,
+            value = in(reg) value << shift,
+        )
+    };
+}
+
+/// 在线程中执行探测函数，探测函数的第一条指令未陷入时返回 `a0`。
+fn probe(entry: usize) -> Option<usize> {
+    let mut thread = Thread::new(entry);
+    unsafe { thread.execute() };
+    let trap = scause::read().cause();
+    if trap == Trap::Exception(Exception::IllegalInstruction) && sepc::read() == entry + 4 {
+        Some(thread.a(0))
+    } else {
+        None
+    }
+}
+
+/// 保存调用者的浮点寄存器和 `fcsr`，填充后标记为干净并发起调用，
+/// 保存调用后的浮点寄存器，最后恢复调用者的浮点寄存器和 `fcsr`。
+///
+/// 只有单精度时使用 `flw` 和 `fsw`，只访问每个槽的低 32 位。
+///
+/// # Safety
+///
+/// 浮点单元必须存在且已打开，`double` 为真时必须支持双精度。
+unsafe fn invoke_float(call: Call, frame: *mut FloatFrame, double: bool) -> FloatState {
+    let [arg0, arg1, arg2, arg3, arg4, arg5] = call.args;
+    let (sstatus, fcsr): (usize, usize);
+    core::arch::asm!(
+        "   .option push
+            .option arch, +d
+            csrr {saved_fcsr}, fcsr
+            beqz {double}, 2f
+        ",
+        r"  .irp n, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31
+                fsd f\n, \n*8({frame})
+                fld f\n, 256+\n*8({frame})
+            .endr
+            j 3f
+        2:
+            .irp n, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31
+                fsw f\n, \n*8({frame})
+                flw f\n, 256+\n*8({frame})
+            .endr
+        3:
+        ",
+        "   csrw fcsr, {fcsr}
+            li   {tmp}, {mask}
+            csrc sstatus, {tmp}
+            li   {tmp}, {clean}
+            csrs sstatus, {tmp}
+            ecall
+            csrr {sstatus}, sstatus
+            csrr {fcsr_out}, fcsr
+            beqz {double}, 4f
+        ",
+        r"  .irp n, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31
+                fsd f\n, 512+\n*8({frame})
+                fld f\n, \n*8({frame})
+            .endr
+            j 5f
+        4:
+            .irp n, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31
+                fsw f\n, 512+\n*8({frame})
+                flw f\n, \n*8({frame})
+            .endr
+        5:
+        ",
+        "   csrw fcsr, {saved_fcsr}
+            .option pop
+        ",
+        frame      = in(reg) frame,
+        double     = in(reg) double as usize,
+        fcsr       = in(reg) FCSR,
+        saved_fcsr = out(reg) _,
+        tmp        = out(reg) _,
+        sstatus    = out(reg) sstatus,
+        fcsr_out   = out(reg) fcsr,
+        mask       = const 0b11 << FS_SHIFT,
+        clean      = const CLEAN << FS_SHIFT,
+        in("a7") call.eid,
+        in("a6") call.fid,
+        inlateout("a0") arg0 => _,
+        inlateout("a1") arg1 => _,
+        in("a2") arg2,
+        in("a3") arg3,
+        in("a4") arg4,
+        in("a5") arg5,
+        options(nostack),
+    );
+    FloatState {
+        fs: (sstatus >> FS_SHIFT) & 0b11,
+        fcsr,
+    }
+}
+
+/// 设置测试用的 `vtype`、`vl` 和 `vcsr` 并读回，然后恢复原值。
+///
+/// # Safety
+///
+/// 向量单元必须存在且已打开。
+unsafe fn vector_csrs() -> VectorCsrs {
+    let (vtype, vl, vcsr);
+    core::arch::asm!(
+        "   .option push
+            .option arch, +v
+            csrr    {saved_vtype}, vtype
+            csrr    {saved_vl}, vl
+            csrr    {saved_vcsr}, vcsr
+            li      {tmp}, 3
+            vsetvli x0, {tmp}, e32, m2, ta, mu
+            csrw    vcsr, {vcsr}
+            csrr    {vtype}, vtype
+            csrr    {vl}, vl
+            csrr    {vcsr}, vcsr
+            vsetvl  x0, {saved_vl}, {saved_vtype}
+            csrw    vcsr, {saved_vcsr}
+            .option pop
+        ",
+        saved_vtype = out(reg) _,
+        saved_vl    = out(reg) _,
+        saved_vcsr  = out(reg) _,
+        tmp         = out(reg) _,
+        vtype       = out(reg) vtype,
+        vl          = out(reg) vl,
+        vcsr        = inout(reg) VCSR => vcsr,
+        options(nostack),
+    );
+    VectorCsrs { vtype, vl, vcsr }
+}
+
+/// 保存调用者的向量寄存器和向量 CSR，填充后标记为干净并发起调用，
+/// 保存调用后的向量寄存器，最后恢复调用者的向量寄存器和向量 CSR。
+///
+/// # Safety
+///
+/// 向量单元必须存在且已打开，向量寄存器文件不能超过 [`VECTOR_FILE`]。
+unsafe fn invoke_vector(call: Call, frame: *mut VectorFrame) -> VectorState {
+    let [arg0, arg1, arg2, arg3, arg4, arg5] = call.args;
+    let (sstatus, vtype, vl, vcsr): (usize, usize, usize, usize);
+    core::arch::asm!(
+        "   .option push
+            .option arch, +v
+            csrr    {saved_vtype}, vtype
+            csrr    {saved_vl}, vl
+            csrr    {saved_vcsr}, vcsr
+            vsetvli {len}, x0, e8, m8, ta, ma
+            mv      {ptr}, {frame}
+            vse8.v  v0, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v8, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v16, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v24, ({ptr})
+            li      {ptr}, {expected}
+            add     {ptr}, {ptr}, {frame}
+            vle8.v  v0, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v8, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v16, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v24, ({ptr})
+            li      {len}, 3
+            vsetvli x0, {len}, e32, m2, ta, mu
+            li      {len}, {vcsr_in}
+            csrw    vcsr, {len}
+            li      {len}, {mask}
+            csrc    sstatus, {len}
+            li      {len}, {clean}
+            csrs    sstatus, {len}
+            ecall
+            csrr    {sstatus}, sstatus
+            csrr    {vtype}, vtype
+            csrr    {vl}, vl
+            csrr    {vcsr}, vcsr
+            vsetvli {len}, x0, e8, m8, ta, ma
+            li      {ptr}, {actual}
+            add     {ptr}, {ptr}, {frame}
+            vse8.v  v0, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v8, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v16, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vse8.v  v24, ({ptr})
+            mv      {ptr}, {frame}
+            vle8.v  v0, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v8, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v16, ({ptr})
+            add     {ptr}, {ptr}, {len}
+            vle8.v  v24, ({ptr})
+            vsetvl  x0, {saved_vl}, {saved_vtype}
+            csrw    vcsr, {saved_vcsr}
+            .option pop
+        ",
+        frame       = in(reg) frame,
+        ptr         = out(reg) _,
+        len         = out(reg) _,
+        saved_vtype = out(reg) _,
+        saved_vl    = out(reg) _,
+        saved_vcsr  = out(reg) _,
+        sstatus     = out(reg) sstatus,
+        vtype       = out(reg) vtype,
+        vl          = out(reg) vl,
+        vcsr        = out(reg) vcsr,
+        expected    = const VECTOR_FILE,
+        actual      = const 2 * VECTOR_FILE,
+        vcsr_in     = const VCSR,
+        mask        = const 0b11 << VS_SHIFT,
+        clean       = const CLEAN << VS_SHIFT,
+        in("a7") call.eid,
+        in("a6") call.fid,
+        inlateout("a0") arg0 => _,
+        inlateout("a1") arg1 => _,
+        in("a2") arg2,
+        in("a3") arg3,
+        in("a4") arg4,
+        in("a5") arg5,
+        options(nostack),
+    );
+    VectorState {
+        vs: (sstatus >> VS_SHIFT) & 0b11,
+        csrs: VectorCsrs { vtype, vl, vcsr },
+    }
+}
+
+/// 读取 `fcsr`，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn probe_float() {
+    core::arch::asm!(
+        "   .option push
+            .option arch, +d
+            csrr a0, fcsr
+            unimp
+            .option pop
+        ",
+        options(noreturn)
+    )
+}
+
+/// 执行一条不改变寄存器值的双精度指令，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn probe_double() {
+    core::arch::asm!(
+        "   .option push
+            .option arch, +d
+            fsgnj.d f0, f0, f0
+            unimp
+            .option pop
+        ",
+        options(noreturn)
+    )
+}
+
+/// 读取 `vlenb`，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn probe_vector() {
+    core::arch::asm!(
+        "   .option push
+            .option arch, +v
+            csrr a0, vlenb
+            unimp
+            .option pop
+        ",
+        options(noreturn)
+    )
+}
@@ -0,0 +1,449 @@
+//! Firmware features extension test suite.
+
+use crate::{
+    binary::{sbi_call_1, sbi_call_3},
+    thread::Thread,
+};
+use riscv::register::scause::{self, Trap};
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// Firmware features extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for firmware features extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test failed for a reserved feature is not rejected.
+    ReservedNotRejected {
+        /// The reserved feature ID.
+        feature: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for a standard feature is not supported by the implementation.
+    NotSupported(u32),
+    /// Test process for the value of a feature has been read.
+    Get {
+        /// The feature ID.
+        feature: u32,
+        /// Current value of the feature.
+        value: usize,
+    },
+    /// Test failed for can't get a feature with [`SbiRet`] error.
+    GetFailed {
+        /// The feature ID.
+        feature: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for can't set a feature with [`SbiRet`] error.
+    SetFailed {
+        /// The feature ID.
+        feature: u32,
+        /// The value passed to the SBI call.
+        value: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for a feature does not hold the value that has been set.
+    ValueMismatch {
+        /// The feature ID.
+        feature: u32,
+        /// The value that has been set.
+        expected: usize,
+        /// The value read from the feature.
+        actual: usize,
+    },
+    /// Test failed for an invalid value or flags is not rejected with `SBI_ERR_INVALID_PARAM`.
+    InvalidParamNotRejected {
+        /// The feature ID.
+        feature: u32,
+        /// The value passed to the SBI call.
+        value: usize,
+        /// The flags passed to the SBI call.
+        flags: usize,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for a misaligned load traps into S-mode when delegation is enabled.
+    MisalignedDelegated,
+    /// Test process for a misaligned load does not trap at all, as hardware supports misaligned access.
+    MisalignedNotTrapped,
+    /// Test failed for a misaligned load traps into S-mode when delegation is disabled.
+    MisalignedNotDelegated,
+    /// Test failed for unexpected trap occurred upon performing a misaligned load.
+    UnexpectedTrap(Trap),
+    /// Test process for a feature is locked and can't be set until reset.
+    Locked(u32),
+    /// Test failed for setting a locked feature is not rejected.
+    LockedSetNotDenied {
+        /// The feature ID.
+        feature: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// All test cases on firmware features extension has passed.
+    Pass,
+}
+
+pub(crate) const EID_FWFT: usize = 0x46574654;
+const SET: usize = 0;
+const GET: usize = 1;
+
+const MISALIGNED_EXC_DELEG: u32 = 0x0;
+const LANDING_PAD: u32 = 0x1;
+const SHADOW_STACK: u32 = 0x2;
+const DOUBLE_TRAP: u32 = 0x3;
+const PTE_AD_HW_UPDATING: u32 = 0x4;
+const POINTER_MASKING_PMLEN: u32 = 0x5;
+
+/// 所有标准特性，以及一个不合法的值。
+const FEATURES: [(u32, usize); 6] = [
+    (MISALIGNED_EXC_DELEG, 2),
+    (LANDING_PAD, 2),
+    (SHADOW_STACK, 2),
+    (DOUBLE_TRAP, 2),
+    (PTE_AD_HW_UPDATING, 2),
+    // 不支持的 PMLEN 会向上取整到支持的长度，只有超过 XLEN 的值一定不合法
+    (POINTER_MASKING_PMLEN, usize::MAX),
+];
+
+/// 保留的特性编号。
+const RESERVED_FEATURES: [u32; 2] = [0x6, 0x3fff_ffff];
+
+/// 用于测试锁定的特性，按顺序选择第一个支持的特性。
+const LOCK_CANDIDATES: [u32; 2] = [PTE_AD_HW_UPDATING, DOUBLE_TRAP];
+
+const FLAG_LOCK: usize = 1 << 0;
+
+/// `SBI_ERR_DENIED_LOCKED`，`sbi-spec` 尚未定义。
+const ERR_DENIED_LOCKED: Error = Error::Custom(-14);
+
+/// Test firmware features extension.
+///
+/// Each standard feature is read, set to another value, read back and
+/// restored. Misaligned exception delegation is checked by performing
+/// a misaligned load in a thread. Feature locking is tested separately
+/// in [`test_lock`].
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_FWFT).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    // 早期草案对保留特性返回 `SBI_ERR_NOT_SUPPORTED`，批准的规范改为 `SBI_ERR_DENIED`
+    for feature in RESERVED_FEATURES {
+        let ret = get(feature);
+        if !matches!(ret.err(), Some(Error::NotSupported | Error::Denied)) {
+            f(Case::ReservedNotRejected { feature, ret });
+            return;
+        }
+    }
+    let mut pass = true;
+    for (feature, invalid) in FEATURES {
+        let ret = get(feature);
+        let original = match ret.err() {
+            None => ret.value,
+            Some(Error::NotSupported) => {
+                f(Case::NotSupported(feature));
+                continue;
+            }
+            Some(_) => {
+                f(Case::GetFailed { feature, ret });
+                pass = false;
+                continue;
+            }
+        };
+        f(Case::Get {
+            feature,
+            value: original,
+        });
+        if !test_set(feature, original, invalid, &mut f) {
+            pass = false;
+            continue;
+        }
+        if feature == MISALIGNED_EXC_DELEG && !test_misaligned(original, &mut f) {
+            pass = false;
+        }
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// Test locking of firmware features.
+///
+/// This test is opt-in, as the first supported feature out of PTE A/D
+/// hardware updating and double trap is locked at its current value, and
+/// stays locked until the system is reset. The kernel booted after this
+/// test can't change the locked feature.
+///
+/// The test case output is to be handled in `f`.
+pub fn test_lock(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_FWFT).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    if lock(&mut f) {
+        f(Case::Pass);
+    }
+}
+
+/// 测试错误路径，然后修改特性的值并读回，最后恢复原值。
+fn test_set(feature: u32, original: usize, invalid: usize, mut f: impl FnMut(Case)) -> bool {
+    for (value, flags) in [(original, !FLAG_LOCK), (invalid, 0)] {
+        let ret = set(feature, value, flags);
+        if ret.err() == Some(ERR_DENIED_LOCKED) {
+            f(Case::Locked(feature));
+            return true;
+        }
+        if ret.err() != Some(Error::InvalidParam) {
+            // 设置可能已经生效，写回原值
+            if ret.is_ok() {
+                let _ = set(feature, original, 0);
+            }
+            f(Case::InvalidParamNotRejected {
+                feature,
+                value,
+                flags,
+                ret,
+            });
+            return false;
+        }
+    }
+    // 修改 PMLEN 会改变 S 态地址的解释，只写回原值
+    let value = match feature {
+        POINTER_MASKING_PMLEN => original,
+        _ => original ^ 1,
+    };
+    let (ret, actual, restore) = set_get_restore(feature, value, original);
+    if ret.is_err() {
+        f(Case::SetFailed {
+            feature,
+            value,
+            ret,
+        });
+        return false;
+    }
+    if actual.is_err() {
+        f(Case::GetFailed {
+            feature,
+            ret: actual,
+        });
+        return false;
+    }
+    if actual.value != value {
+        f(Case::ValueMismatch {
+            feature,
+            expected: value,
+            actual: actual.value,
+        });
+        return false;
+    }
+    if restore.is_err() {
+        f(Case::SetFailed {
+            feature,
+            value: original,
+            ret: restore,
+        });
+        return false;
+    }
+    true
+}
+
+/// 分别打开和关闭非对齐异常委托，在线程中执行非对齐访存，检查 S 态是否陷入，最后恢复原值。
+fn test_misaligned(original: usize, mut f: impl FnMut(Case)) -> bool {
+    use riscv::register::scause::Exception;
+
+    /// 非对齐加载异常码。
+    const LOAD_MISALIGNED: usize = 4;
+
+    let mut pass = true;
+    for value in [1, 0] {
+        let ret = set(MISALIGNED_EXC_DELEG, value, 0);
+        // 已锁定的特性在设置时已经报告
+        if ret.err() == Some(ERR_DENIED_LOCKED) {
+            return true;
+        }
+        if ret.is_err() {
+            f(Case::SetFailed {
+                feature: MISALIGNED_EXC_DELEG,
+                value,
+                ret,
+            });
+            pass = false;
+            break;
+        }
+        let scause = trap_misaligned();
+        let trap = scause.cause();
+        let misaligned = !scause.is_interrupt() && scause.code() == LOAD_MISALIGNED;
+        if misaligned && value == 0 {
+            f(Case::MisalignedNotDelegated);
+            pass = false;
+            break;
+        } else if misaligned {
+            f(Case::MisalignedDelegated);
+        } else if trap != Trap::Exception(Exception::IllegalInstruction) {
+            f(Case::UnexpectedTrap(trap));
+            pass = false;
+            break;
+        } else if value == 1 {
+            f(Case::MisalignedNotTrapped);
+        }
+    }
+    let ret = set(MISALIGNED_EXC_DELEG, original, 0);
+    if pass && ret.is_err() {
+        f(Case::SetFailed {
+            feature: MISALIGNED_EXC_DELEG,
+            value: original,
+            ret,
+        });
+        return false;
+    }
+    pass
+}
+
+/// 在线程中执行一次非对齐加载，返回线程陷入时的 `scause`。
+///
+/// 非对齐访存未陷入时，线程以非法指令陷入。
+fn trap_misaligned() -> scause::Scause {
+    fn load(addr: usize) -> ! {
+        unsafe {
+            core::arch::asm!(
+                "   lw   {0}, 0({0})
+                    unimp
+                ",
+                in(reg) addr,
+                options(noreturn)
+            )
+        };
+    }
+
+    let mut stack = [0usize; 32];
+    let mut thread = Thread::new(load as _);
+    *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
+    *thread.a_mut(0) = stack.as_ptr() as usize + 1;
+    unsafe { thread.execute() };
+    scause::read()
+}
+
+/// 以当前值锁定一个特性，检查锁定后无法再设置。
+fn lock(mut f: impl FnMut(Case)) -> bool {
+    let Some((feature, value)) = LOCK_CANDIDATES
+        .into_iter()
+        .find_map(|feature| get(feature).ok().map(|value| (feature, value)))
+    else {
+        return true;
+    };
+    let ret = set(feature, value, FLAG_LOCK);
+    if ret.err() == Some(ERR_DENIED_LOCKED) {
+        f(Case::Locked(feature));
+        return true;
+    }
+    if ret.is_err() {
+        f(Case::SetFailed {
+            feature,
+            value,
+            ret,
+        });
+        return false;
+    }
+    let actual = get(feature);
+    if actual.is_err() {
+        f(Case::GetFailed {
+            feature,
+            ret: actual,
+        });
+        return false;
+    }
+    if actual.value != value {
+        f(Case::ValueMismatch {
+            feature,
+            expected: value,
+            actual: actual.value,
+        });
+        return false;
+    }
+    // 早期草案对已锁定的特性返回 `SBI_ERR_DENIED`
+    for value in [value, value ^ 1] {
+        let ret = set(feature, value, 0);
+        if !matches!(ret.err(), Some(ERR_DENIED_LOCKED | Error::Denied)) {
+            f(Case::LockedSetNotDenied { feature, ret });
+            return false;
+        }
+    }
+    f(Case::Locked(feature));
+    true
+}
+
+/// 设置特性并读回，然后恢复原值，返回三次调用的结果。
+///
+/// 打开着陆点或影子栈检查后，间接跳转和函数返回都可能陷入，因此三次调用在同一段汇编中完成。
+fn set_get_restore(feature: u32, value: usize, original: usize) -> (SbiRet, SbiRet, SbiRet) {
+    let (set_error, set_value, get_error, get_value, restore_error, restore_value);
+    unsafe {
+        core::arch::asm!(
+            "   mv   a0, {feature}
+                mv   a1, {value}
+                li   a2, 0
+                li   a6, {set}
+                ecall
+                mv   {set_error}, a0
+                mv   {set_value}, a1
+                mv   a0, {feature}
+                li   a6, {get}
+                ecall
+                mv   {get_error}, a0
+                mv   {get_value}, a1
+                mv   a0, {feature}
+                mv   a1, {original}
+                li   a2, 0
+                li   a6, {set}
+                ecall
+            ",
+            feature   = in(reg) feature as usize,
+            value     = in(reg) value,
+            original  = in(reg) original,
+            set       = const SET,
+            get       = const GET,
+            set_error = out(reg) set_error,
+            set_value = out(reg) set_value,
+            get_error = out(reg) get_error,
+            get_value = out(reg) get_value,
+            in("a7") EID_FWFT,
+            out("a0") restore_error,
+            out("a1") restore_value,
+            out("a2") _,
+            out("a6") _,
+        );
+    }
+    (
+        SbiRet {
+            error: set_error,
+            value: set_value,
+        },
+        SbiRet {
+            error: get_error,
+            value: get_value,
+        },
+        SbiRet {
+            error: restore_error,
+            value: restore_value,
+        },
+    )
+}
+
+#[inline]
+fn set(feature: u32, value: usize, flags: usize) -> SbiRet {
+    sbi_call_3(EID_FWFT, SET, feature as _, value, flags)
+}
+
+#[inline]
+fn get(feature: u32) -> SbiRet {
+    sbi_call_1(EID_FWFT, GET, feature as _)
+}
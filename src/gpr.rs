@@ -0,0 +1,131 @@
+//! Cross-extension test suite on preservation of general purpose registers.
+
+use crate::{binary::harmless_calls, thread::Thread};
+use riscv::register::{
+    scause::{self, Trap},
+    sepc,
+};
+
+/// General purpose register preservation test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Test begin.
+    Begin,
+    /// Test process for all general purpose registers except `a0` and `a1` are preserved across an SBI call.
+    Preserved {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+    },
+    /// Test failed for a general purpose register is clobbered by an SBI call.
+    Clobbered {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// ABI name of the clobbered register.
+        register: &'static str,
+        /// The value before the SBI call.
+        expected: usize,
+        /// The value after the SBI call.
+        actual: usize,
+    },
+    /// Test failed for an SBI call trapped into supervisor mode.
+    Trapped {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// The trap caught upon the SBI call.
+        trap: Trap,
+    },
+    /// All test cases on general purpose register preservation has passed.
+    Pass,
+}
+
+/// 通用寄存器的 ABI 名字。
+const REGISTERS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// 寄存器填充值的高位，低位为寄存器编号。
+const PATTERN: usize = 0x5a5a_a5a5_5a5a_a500_u64 as usize;
+
+/// Test that SBI calls preserve general purpose registers.
+///
+/// Every available extension is called with a side-effect free function.
+/// Each call is made in a thread from a naked stub, with every general purpose
+/// register other than the call arguments filled with a distinct pattern,
+/// including `sp`, `gp` and `tp`. All registers other than `a0` and `a1`
+/// are compared after the call. Current hart `hartid` is used as parameter
+/// of `HSM` status call.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hartid: usize, mut f: impl FnMut(Case)) {
+    use scause::Exception;
+
+    f(Case::Begin);
+    let mut pass = true;
+    for call in harmless_calls(hartid) {
+        let (eid, fid) = (call.eid, call.fid);
+        let entry = stub as usize;
+        let mut thread = Thread::new(entry);
+        for n in 1..32 {
+            *thread.x_mut(n) = PATTERN | n;
+        }
+        for (i, arg) in call.args.into_iter().enumerate() {
+            *thread.a_mut(i) = arg;
+        }
+        *thread.a_mut(6) = fid;
+        *thread.a_mut(7) = eid;
+        let expected: [usize; 31] = core::array::from_fn(|i| thread.x(i + 1));
+        unsafe { thread.execute() };
+        let trap = scause::read().cause();
+        // 调用返回后应在其后的 `unimp` 处陷入
+        if trap != Trap::Exception(Exception::IllegalInstruction) || sepc::read() != entry + 4 {
+            f(Case::Trapped { eid, fid, trap });
+            pass = false;
+            continue;
+        }
+        let mut preserved = true;
+        // a0 和 a1 是返回值
+        for n in (1..32).filter(|n| !(10..=11).contains(n)) {
+            let (expected, actual) = (expected[n - 1], thread.x(n));
+            if actual != expected {
+                f(Case::Clobbered {
+                    eid,
+                    fid,
+                    register: REGISTERS[n],
+                    expected,
+                    actual,
+                });
+                preserved = false;
+            }
+        }
+        if preserved {
+            f(Case::Preserved { eid, fid });
+        }
+        pass &= preserved;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 以线程上下文中的寄存器发起调用，返回后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn stub() {
+    core::arch::asm!(
+        "   ecall
+            unimp
+        ",
+        options(noreturn)
+    )
+}
@@ -1,6 +1,7 @@
 //! Hart state monitor extension test suite.
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use crate::batch::{self, Batches, Event, ItemPerHart, STAGE_IDLE};
+use core::sync::atomic::Ordering;
 use sbi::SbiRet;
 use sbi_spec::hsm::hart_state;
 
@@ -47,7 +48,7 @@ pub enum Case<'a> {
 /// The test case output is to be handled in `f`.
 pub fn test(
     primary_hart_id: usize,
-    mut hart_mask: usize,
+    hart_mask: usize,
     hart_mask_base: usize,
     mut f: impl FnMut(Case),
 ) {
@@ -58,49 +59,23 @@ pub fn test(
     }
     f(Case::Begin);
     // 分批测试
-
-    let mut batch = [0usize; TEST_BATCH_SIZE];
-    let mut batch_count = 0;
-    let mut batch_size = 0;
-    let mut hartid = hart_mask_base;
-    while hart_mask != 0 {
-        if hartid != primary_hart_id {
-            // 副核在测试前必须处于停止状态
-            if sbi::hart_get_status(hartid) == STOPPED {
-                batch[batch_size] = hartid;
-                batch_size += 1;
-                // 收集一个批次，执行测试
-                if batch_size == TEST_BATCH_SIZE {
-                    if test_batch(&batch, &mut f) {
-                        batch_count += 1;
-                        batch_size = 0;
-                    } else {
-                        return;
-                    }
+    let result =
+        batch::for_each_batch(
+            primary_hart_id,
+            hart_mask,
+            hart_mask_base,
+            |event| match event {
+                Event::StartedBeforeTest(hartid) => {
+                    f(Case::HartStartedBeforeTest(hartid));
+                    true
                 }
-            }
-            // 副核不在停止状态
-            else {
-                f(Case::HartStartedBeforeTest(hartid));
-            }
-        }
-        let distance = hart_mask.trailing_zeros() + 1;
-        hart_mask >>= distance;
-        hartid += distance as usize;
-    }
-    // 为不满一批次的核执行测试
-    if batch_size > 0 {
-        if test_batch(&batch[..batch_size], &mut f) {
-            f(Case::Pass);
-        }
-    }
-    // 所有批次通过测试
-    else if batch_count > 0 {
-        f(Case::Pass);
-    }
-    // 没有找到能参与测试的副核
-    else {
-        f(Case::NoStoppedHart)
+                Event::Batch(batch) => test_batch(batch, &mut f),
+            },
+        );
+    match result {
+        Batches::Passed => f(Case::Pass),
+        Batches::Failed => {}
+        Batches::NoStoppedHart => f(Case::NoStoppedHart),
     }
 }
 
@@ -108,72 +83,15 @@ const STARTED: SbiRet = SbiRet::success(hart_state::STARTED);
 const STOPPED: SbiRet = SbiRet::success(hart_state::STOPPED);
 const SUSPENDED: SbiRet = SbiRet::success(hart_state::SUSPENDED);
 
-const TEST_BATCH_SIZE: usize = 4;
-static mut STACK: [ItemPerHart; TEST_BATCH_SIZE] = [ItemPerHart::ZERO; TEST_BATCH_SIZE];
-
-#[repr(C, align(512))]
-struct ItemPerHart {
-    stage: AtomicU32,
-    signal: AtomicU32,
-    stack: [u8; 504],
-}
-
-const STAGE_IDLE: u32 = 0;
 const STAGE_STARTED: u32 = 1;
 const STAGE_RESUMED: u32 = 2;
 
-impl ItemPerHart {
-    #[allow(clippy::declare_interior_mutable_const)]
-    const ZERO: Self = Self {
-        stage: AtomicU32::new(STAGE_IDLE),
-        signal: AtomicU32::new(0),
-        stack: [0; 504],
-    };
-
-    #[inline]
-    fn reset(&mut self) -> *const ItemPerHart {
-        self.stage.store(STAGE_IDLE, Ordering::Relaxed);
-        self as _
-    }
-
-    #[inline]
-    fn wait_start(&self) {
-        while self.stage.load(Ordering::Relaxed) != STAGE_STARTED {
-            core::hint::spin_loop();
-        }
-    }
-
-    #[inline]
-    fn wait_resume(&self) {
-        while self.stage.load(Ordering::Relaxed) != STAGE_RESUMED {
-            core::hint::spin_loop();
-        }
-    }
-
-    #[inline]
-    fn send_signal(&self) {
-        self.signal.store(1, Ordering::Release);
-    }
-
-    #[inline]
-    fn wait_signal(&self) {
-        while self
-            .signal
-            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
-            .is_err()
-        {
-            core::hint::spin_loop();
-        }
-    }
-}
-
 /// 测试一批核
 fn test_batch(batch: &[usize], mut f: impl FnMut(Case)) -> bool {
     f(Case::BatchBegin(batch));
     // 初始这些核都是停止状态，测试 start
     for (i, hartid) in batch.iter().copied().enumerate() {
-        let ptr = unsafe { STACK[i].reset() };
-        let ret = sbi::hart_start(hartid, test_entry as _, ptr as _);
+        let ret = batch::start(hartid, i, rust_main);
         if ret.is_err() {
             f(Case::HartStartFailed { hartid, ret });
             return false;
@@ -181,14 +99,14 @@ fn test_batch(batch: &[usize], mut f: impl FnMut(Case)) -> bool {
     }
     // 测试不可恢复休眠
     for (i, hartid) in batch.iter().copied().enumerate() {
-        let item = unsafe { &mut STACK[i] };
+        let item = batch::item(i);
         // 等待完成启动
         while sbi::hart_get_status(hartid) != STARTED {
             core::hint::spin_loop();
         }
         f(Case::HartStarted(hartid));
         // 等待信号
-        item.wait_start();
+        item.wait_stage(STAGE_STARTED);
         // 发出信号
         item.send_signal();
         // 等待完成休眠
@@ -198,21 +116,17 @@ fn test_batch(batch: &[usize], mut f: impl FnMut(Case)) -> bool {
         f(Case::HartSuspendedNonretentive(hartid));
     }
     // 全部唤醒
-    let mut mask = 1usize;
-    for hartid in &batch[1..] {
-        mask |= 1 << (hartid - batch[0]);
-    }
-    sbi::send_ipi(sbi_spec::binary::HartMask::from_mask_base(mask, batch[0]));
+    sbi::send_ipi(batch::hart_mask(batch));
     // 测试可恢复休眠
     for (i, hartid) in batch.iter().copied().enumerate() {
-        let item = unsafe { &mut STACK[i] };
+        let item = batch::item(i);
         // 等待完成恢复
         while sbi::hart_get_status(hartid) != STARTED {
             core::hint::spin_loop();
         }
         f(Case::HartResumed(hartid));
         // 等待信号
-        item.wait_resume();
+        item.wait_stage(STAGE_RESUMED);
         // 发出信号
         item.send_signal();
         // 等待完成休眠
@@ -232,24 +146,6 @@ fn test_batch(batch: &[usize], mut f: impl FnMut(Case)) -> bool {
     true
 }
 
-/// 测试用启动入口
-#[naked]
-unsafe extern "C" fn test_entry(hartid: usize, opaque: *mut ItemPerHart) -> ! {
-    core::arch::asm!(
-        "csrw sie, zero",   // 关中断
-        "call {set_stack}", // 设置栈
-        "j    {rust_main}", // 进入 rust
-        set_stack = sym set_stack,
-        rust_main = sym rust_main,
-        options(noreturn),
-    )
-}
-
-#[naked]
-unsafe extern "C" fn set_stack(hart_id: usize, ptr: *const ItemPerHart) {
-    core::arch::asm!("addi sp, a1, 512", "ret", options(noreturn));
-}
-
 #[inline(never)]
 extern "C" fn rust_main(hart_id: usize, opaque: *mut ItemPerHart) -> ! {
     let item = unsafe { &mut *opaque };
@@ -261,13 +157,13 @@ extern "C" fn rust_main(hart_id: usize, opaque: *mut ItemPerHart) -> ! {
     ) {
         Ok(_) => {
             item.wait_signal();
-            let ret = sbi::hart_suspend(sbi::NonRetentive, test_entry as _, opaque as _);
+            let ret = batch::suspend_nonretentive(opaque);
             unreachable!("suspend [{hart_id}] but {ret:?}")
         }
         Err(STAGE_STARTED) => {
             item.stage.store(STAGE_RESUMED, Ordering::Release);
             item.wait_signal();
-            let _ = sbi::hart_suspend(sbi::Retentive, test_entry as _, opaque as _);
+            let _ = batch::suspend_retentive(opaque);
             let ret = sbi::hart_stop();
             unreachable!("suspend [{hart_id}] but {ret:?}")
         }
@@ -0,0 +1,334 @@
+//! Legacy extensions test suite.
+
+#![allow(deprecated)]
+
+use crate::thread::Thread;
+use riscv::register::{
+    scause::{self, Interrupt, Trap},
+    sie, sip,
+};
+use sbi::{legacy::*, SbiRet};
+
+/// Legacy extensions test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for none of legacy extensions exists.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for whether a legacy extension is implemented.
+    Probe {
+        /// The legacy extension ID.
+        eid: usize,
+        /// Whether the legacy extension is implemented.
+        implemented: bool,
+    },
+    /// Test failed for a legacy call returned with error.
+    CallFailed {
+        /// The legacy extension ID.
+        eid: usize,
+        /// The value returned in `a0` register.
+        ret: usize,
+    },
+    /// Test process for a character has been read from legacy console,
+    /// or `None` if no character is pending.
+    Getchar(Option<u8>),
+    /// Test failed for legacy console returned a value other than a byte or `-1`.
+    GetcharInvalid(usize),
+    /// Test process for timer set by legacy call has been triggered.
+    SetTimer,
+    /// Test failed for timer set by legacy call is triggered before the expected time.
+    TimerEarly {
+        /// The time value passed to the legacy call.
+        expected: u64,
+        /// The time value upon timer interrupt.
+        actual: u64,
+    },
+    /// Test failed for pending timer interrupt is not cleared by setting timer again.
+    TimerNotCleared,
+    /// Test process for inter-processor interrupt has been sent to current hart.
+    SendIpi {
+        /// Whether the interrupt is sent by legacy call, or by `sPI` extension otherwise.
+        legacy: bool,
+    },
+    /// Test failed for pending inter-processor interrupt is not cleared by legacy call.
+    IpiNotCleared,
+    /// Test failed for unexpected trap occurred upon waiting for an interrupt.
+    UnexpectedTrap(Trap),
+    /// Test process for a legacy remote fence has been performed on current hart.
+    RemoteFence(usize),
+    /// Test failed for the `RFNC` equivalent of a legacy remote fence returned with [`SbiRet`] error.
+    RfncFailed {
+        /// The legacy extension ID.
+        eid: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Inter-processor interrupt and remote fence tests are skipped for current hart can't be
+    /// expressed in a legacy hart mask.
+    HartIdOutOfRange(usize),
+    /// Process for shutting down the system through legacy extension.
+    Shutdown,
+    /// Failed for legacy shutdown returned.
+    ShutdownReturned,
+    /// All test cases on legacy extensions has passed.
+    Pass,
+}
+
+/// 所有旧版扩展。
+pub(crate) const LEGACY_EXTENSIONS: [usize; 9] = [
+    LEGACY_SET_TIMER,
+    LEGACY_CONSOLE_PUTCHAR,
+    LEGACY_CONSOLE_GETCHAR,
+    LEGACY_CLEAR_IPI,
+    LEGACY_SEND_IPI,
+    LEGACY_REMOTE_FENCE_I,
+    LEGACY_REMOTE_SFENCE_VMA,
+    LEGACY_REMOTE_SFENCE_VMA_ASID,
+    LEGACY_SHUTDOWN,
+];
+
+/// Test legacy extensions, except console input and shutdown.
+///
+/// Each legacy call is cross-checked against `TIME`, `sPI` or `RFNC`
+/// extension if available. Current hart `hartid` is used as target of
+/// inter-processor interrupts and remote fences, and timer is set
+/// to `delay` from now.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hartid: usize, delay: u64, mut f: impl FnMut(Case)) {
+    let mut implemented = [false; LEGACY_EXTENSIONS.len()];
+    for (i, eid) in LEGACY_EXTENSIONS.into_iter().enumerate() {
+        implemented[i] = sbi::probe_extension(eid).is_available();
+    }
+    if !implemented.contains(&true) {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    for (eid, implemented) in LEGACY_EXTENSIONS.into_iter().zip(implemented) {
+        f(Case::Probe { eid, implemented });
+    }
+    let implemented = |eid: usize| implemented[eid];
+    let mut pass = true;
+    // 控制台
+    if implemented(LEGACY_CONSOLE_PUTCHAR) {
+        // 回车不会打乱测试输出
+        let ret = console_putchar(b'\r' as _);
+        if ret != 0 {
+            f(Case::CallFailed {
+                eid: LEGACY_CONSOLE_PUTCHAR,
+                ret,
+            });
+            pass = false;
+        }
+    }
+    // 定时器
+    if implemented(LEGACY_SET_TIMER) && !test_timer(delay, &mut f) {
+        pass = false;
+    }
+    // 核间中断和远程屏障
+    match 1usize.checked_shl(hartid as _) {
+        Some(hart_mask) => {
+            if !test_ipi(hart_mask, implemented, &mut f) {
+                pass = false;
+            }
+            if !test_remote_fence(hart_mask, implemented, &mut f) {
+                pass = false;
+            }
+        }
+        None => f(Case::HartIdOutOfRange(hartid)),
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// Test reading a character from legacy console.
+///
+/// This test is opt-in, as it consumes pending console input.
+///
+/// The test case output is to be handled in `f`.
+pub fn test_getchar(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(LEGACY_CONSOLE_GETCHAR).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    match console_getchar() {
+        usize::MAX => f(Case::Getchar(None)),
+        c if c <= 0xff => f(Case::Getchar(Some(c as _))),
+        c => {
+            f(Case::GetcharInvalid(c));
+            return;
+        }
+    }
+    f(Case::Pass);
+}
+
+/// Shut down the system through legacy extension.
+///
+/// This test is opt-in, as it does not return on success.
+/// If legacy shutdown is not implemented, or it returns, current hart is parked.
+///
+/// The process output is to be handled in `f`.
+pub fn test_shutdown(mut f: impl FnMut(Case)) -> ! {
+    if sbi::probe_extension(LEGACY_SHUTDOWN).is_available() {
+        f(Case::Shutdown);
+        // 不使用 `sbi::legacy::shutdown`，它在调用返回时会恐慌
+        unsafe { core::arch::asm!("ecall", in("a7") LEGACY_SHUTDOWN, lateout("a0") _) };
+        f(Case::ShutdownReturned);
+    } else {
+        f(Case::NotExist);
+    }
+    loop {
+        unsafe { riscv::asm::wfi() };
+    }
+}
+
+/// 以旧版调用设置定时器并等待中断，然后以新版调用清除。
+fn test_timer(delay: u64, mut f: impl FnMut(Case)) -> bool {
+    use crate::time::wait_timer;
+    use riscv::register::time;
+
+    let expected = time::read64() + delay;
+    let ret = set_timer(expected);
+    if ret != 0 {
+        f(Case::CallFailed {
+            eid: LEGACY_SET_TIMER,
+            ret,
+        });
+        return false;
+    }
+    let trap = wait_timer();
+    let actual = time::read64();
+    // 时钟扩展可用时交叉检查，以新版调用清除旧版调用设置的定时器
+    if sbi::probe_extension(sbi::Timer).is_available() {
+        sbi::set_timer(u64::MAX);
+    } else {
+        set_timer(u64::MAX);
+    }
+    if trap != Trap::Interrupt(Interrupt::SupervisorTimer) {
+        f(Case::UnexpectedTrap(trap));
+        return false;
+    }
+    if actual < expected {
+        f(Case::TimerEarly { expected, actual });
+        return false;
+    }
+    f(Case::SetTimer);
+    if sip::read().stimer() {
+        f(Case::TimerNotCleared);
+        return false;
+    }
+    true
+}
+
+/// 分别以旧版调用和 sPI 扩展向当前核发送核间中断，然后以旧版调用清除。
+fn test_ipi(
+    hart_mask: usize,
+    implemented: impl Fn(usize) -> bool,
+    mut f: impl FnMut(Case),
+) -> bool {
+    const SSIP: usize = 1 << 1;
+
+    /// 在线程中发送核间中断，`legacy` 为零时使用 sPI 扩展。
+    fn ipi(hart_mask: usize, legacy: usize) -> ! {
+        if legacy != 0 {
+            send_ipi(&hart_mask as *const _ as _);
+        } else {
+            sbi::send_ipi(sbi_spec::binary::HartMask::from_mask_base(hart_mask, 0));
+        }
+        // 必须立即触发中断，即使是一个指令的延迟，也会触发另一个异常
+        unsafe { core::arch::asm!("unimp", options(noreturn, nomem)) };
+    }
+
+    let senders = [
+        (true, implemented(LEGACY_SEND_IPI)),
+        (false, sbi::probe_extension(sbi::Ipi).is_available()),
+    ];
+    for (legacy, available) in senders {
+        if !available {
+            continue;
+        }
+        let mut stack = [0usize; 32];
+        let mut thread = Thread::new(ipi as _);
+        *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
+        *thread.a_mut(0) = hart_mask;
+        *thread.a_mut(1) = legacy as _;
+        unsafe {
+            sie::set_ssoft();
+            thread.execute();
+        }
+        let trap = scause::read().cause();
+        if trap != Trap::Interrupt(Interrupt::SupervisorSoft) {
+            f(Case::UnexpectedTrap(trap));
+            return false;
+        }
+        f(Case::SendIpi { legacy });
+        // 交叉检查，以旧版调用清除任一方式发送的核间中断
+        if implemented(LEGACY_CLEAR_IPI) {
+            let ret = clear_ipi();
+            if ret != 0 {
+                f(Case::CallFailed {
+                    eid: LEGACY_CLEAR_IPI,
+                    ret,
+                });
+                return false;
+            }
+            if sip::read().ssoft() {
+                unsafe { core::arch::asm!("csrc sip, {}", in(reg) SSIP) };
+                f(Case::IpiNotCleared);
+                return false;
+            }
+        } else {
+            unsafe { core::arch::asm!("csrc sip, {}", in(reg) SSIP) };
+        }
+    }
+    true
+}
+
+/// 在当前核上执行旧版远程屏障，RFNC 扩展可用时以新版调用交叉检查。
+fn test_remote_fence(
+    hart_mask: usize,
+    implemented: impl Fn(usize) -> bool,
+    mut f: impl FnMut(Case),
+) -> bool {
+    use sbi_spec::binary::HartMask;
+
+    let rfnc = sbi::probe_extension(sbi::Fence).is_available();
+    let mask = &hart_mask as *const usize as usize;
+    let modern = HartMask::from_mask_base(hart_mask, 0);
+    for eid in [
+        LEGACY_REMOTE_FENCE_I,
+        LEGACY_REMOTE_SFENCE_VMA,
+        LEGACY_REMOTE_SFENCE_VMA_ASID,
+    ] {
+        if !implemented(eid) {
+            continue;
+        }
+        let ret = match eid {
+            LEGACY_REMOTE_FENCE_I => remote_fence_i(mask),
+            LEGACY_REMOTE_SFENCE_VMA => remote_fence_vma(mask, 0, usize::MAX),
+            _ => remote_fence_vma_asid(mask, 0, usize::MAX, 0),
+        };
+        if ret != 0 {
+            f(Case::CallFailed { eid, ret });
+            return false;
+        }
+        f(Case::RemoteFence(eid));
+        if !rfnc {
+            continue;
+        }
+        let ret = match eid {
+            LEGACY_REMOTE_FENCE_I => sbi::remote_fence_i(modern),
+            LEGACY_REMOTE_SFENCE_VMA => sbi::remote_sfence_vma(modern, 0, usize::MAX),
+            _ => sbi::remote_sfence_vma_asid(modern, 0, usize::MAX, 0),
+        };
+        if ret.is_err() {
+            f(Case::RfncFailed { eid, ret });
+            return false;
+        }
+    }
+    true
+}
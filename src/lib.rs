@@ -4,6 +4,8 @@
 #![deny(warnings, missing_docs)]
 #![feature(naked_functions, asm_const)]
 
+mod batch;
+mod binary;
 mod thread;
 
 pub extern crate sbi_rt as sbi;
@@ -19,7 +21,16 @@ pub use log_test::Testing;
 
 // §4
 mod base;
-pub use base::{test as test_base, Case as BaseCase, Extensions};
+pub use base::{
+    test as test_base, test_with_sweep as test_base_with_sweep, Case as BaseCase, ExtensionSweep,
+    Extensions, ImplVersion,
+};
+// §5
+mod legacy;
+pub use legacy::{
+    test as test_legacy, test_getchar as test_legacy_getchar,
+    test_shutdown as test_legacy_shutdown, Case as LegacyCase,
+};
 // §6
 mod time;
 pub use time::{test as test_timer, Case as TimerCase};
@@ -27,14 +38,53 @@ pub use time::{test as test_timer, Case as TimerCase};
 mod spi;
 pub use spi::{test as test_ipi, Case as IpiCase};
 // §8
-// pub mod rfnc;
+mod rfnc;
+pub use rfnc::{test as test_rfnc, Case as RfncCase};
 // §9
 mod hsm;
 pub use hsm::{test as test_hsm, Case as HsmCase};
 // §10
-// pub mod srst;
+mod srst;
+pub use srst::{
+    shutdown, test as test_srst, test_reboot as test_srst_reboot,
+    test_reboot_observed as test_srst_reboot_observed, Case as SrstCase, Reboot, ShutdownCase,
+};
 // §11
-// pub mod pmu;
+mod pmu;
+pub use pmu::{test as test_pmu, Case as PmuCase, CounterInfo};
 // §12
 mod dbcn;
 pub use dbcn::{test as test_dbcn, Case as DbcnCase};
+// §13
+mod susp;
+pub use susp::{test as test_susp, Case as SuspCase};
+// §14
+mod cppc;
+pub use cppc::{test as test_cppc, Case as CppcCase};
+// §15
+mod nacl;
+pub use nacl::{test as test_nacl, Case as NaclCase};
+// §16
+mod sta;
+pub use sta::{test as test_sta, Case as StaCase};
+// §17
+mod sse;
+pub use sse::{test as test_sse, Case as SseCase};
+// §18
+mod fwft;
+pub use fwft::{test as test_fwft, test_lock as test_fwft_lock, Case as FwftCase};
+// §19
+mod dbtr;
+pub use dbtr::{test as test_dbtr, Case as DbtrCase};
+// §20
+mod mpxy;
+pub use mpxy::{test as test_mpxy, Case as MpxyCase, Message as MpxyMessage};
+// 跨扩展
+mod negative;
+pub use negative::{test as test_negative, Case as NegativeCase};
+mod gpr;
+pub use gpr::{test as test_gpr, Case as GprCase};
+mod fpvec;
+pub use fpvec::{test as test_fp_vector, Case as FpVectorCase};
+mod scsr;
+pub use scsr::{test as test_scsr, Case as ScsrCase};
@@ -1,13 +1,17 @@
-﻿use crate::{base, dbcn, hsm, spi, time};
+﻿use crate::{
+    base, cppc, dbcn, dbtr, fpvec, fwft, gpr, hsm, legacy, mpxy, nacl, negative, pmu, rfnc, scsr,
+    spi, srst, sse, sta, susp, time, Extensions,
+};
 use log_crate::*;
+use sbi::ExtensionInfo;
 
 /// Automatic SBI testing with logging enabled.
 pub struct Testing {
     /// The hart ID to test most of single core extensions.
     pub hartid: usize,
-    /// A list of harts to test Hart State Monitor extension.
+    /// A list of harts to be brought up or checked by multi-hart tests.
     pub hart_mask: usize,
-    /// Base of hart list to test Hart State Monitor extension.
+    /// Base of hart list `hart_mask`.
     pub hart_mask_base: usize,
     /// Delay value to test Timer programmer extension.
     pub delay: u64,
@@ -19,6 +23,7 @@ impl Testing {
     /// Start testing process of RISC-V SBI implementation.
     pub fn test(self) -> bool {
         let mut result = true;
+        let mut exts = None;
         base::test(|case| {
             use base::Case::*;
             match case {
@@ -34,11 +39,19 @@ impl Testing {
                 GetSbiImplId(Err(unknown)) => {
                     warn!(target: TARGET, "unknown sbi impl = {unknown:#x}");
                 }
+                SpecVersionReservedBit(raw) => {
+                    error!(target: TARGET, "sbi spec version {raw:#x} has reserved bit 31 set");
+                    result = false;
+                }
+                UnknownSpecVersion(version) => {
+                    warn!(target: TARGET, "sbi spec version {version} is not a known release");
+                }
                 GetSbiImplVersion(version) => {
-                    info!(target: TARGET, "sbi impl version = {version:#x}");
+                    info!(target: TARGET, "sbi impl version = {version}");
                 }
-                ProbeExtensions(exts) => {
-                    info!(target: TARGET, "sbi extensions = {exts}");
+                ProbeExtensions(probed) => {
+                    info!(target: TARGET, "sbi extensions = {probed}");
+                    exts = Some(probed);
                 }
                 GetMVendorId(id) => {
                     info!(target: TARGET, "mvendor id = {id:#x}");
@@ -49,119 +62,1147 @@ impl Testing {
                 GetMimpId(id) => {
                     info!(target: TARGET, "mimp id = {id:#x}");
                 }
+                VendorExtension(eid, value) => {
+                    info!(target: TARGET, "extension {eid:#x} found, probe value = {value:#x}");
+                }
             }
         });
-        time::test(self.delay, |case| {
-            use time::Case::*;
-            match case {
-                NotExist => {
-                    error!(target: TARGET, "Sbi `TIME` not exist");
-                    result = false;
+        // 以基础扩展的探测结果决定测试哪些扩展
+        let exts = exts.unwrap_or_else(Extensions::probe);
+        if exts.legacy.iter().any(ExtensionInfo::is_available) {
+            legacy::test(self.hartid, self.delay, |case| {
+                use legacy::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `Legacy`"),
+                    Pass => info!(target: TARGET, "Sbi `Legacy` test pass"),
+                    Probe { eid, implemented } => {
+                        debug!(target: TARGET, "legacy extension {eid:#x} implemented = {implemented}");
+                    }
+                    CallFailed { eid, ret } => {
+                        error!(target: TARGET, "legacy call {eid:#x} failed: {ret:#x}");
+                        result = false;
+                    }
+                    Getchar(Some(c)) => info!(target: TARGET, "legacy console read {c:#x}"),
+                    Getchar(None) => {
+                        info!(target: TARGET, "legacy console has no pending character")
+                    }
+                    GetcharInvalid(ret) => {
+                        error!(target: TARGET, "legacy console returned invalid value {ret:#x}");
+                        result = false;
+                    }
+                    SetTimer => info!(target: TARGET, "legacy timer triggered"),
+                    TimerEarly { expected, actual } => {
+                        error!(
+                            target: TARGET,
+                            "legacy timer expected at {expected}, but triggered at {actual}"
+                        );
+                        result = false;
+                    }
+                    TimerNotCleared => {
+                        error!(target: TARGET, "timer interrupt not cleared by setting timer again");
+                        result = false;
+                    }
+                    SendIpi { legacy } => {
+                        info!(target: TARGET, "ipi received, sent by legacy call = {legacy}");
+                    }
+                    IpiNotCleared => {
+                        error!(target: TARGET, "ipi not cleared by legacy call");
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at supervisor timer or soft interrupt, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    RemoteFence(eid) => {
+                        debug!(target: TARGET, "legacy remote fence {eid:#x} performed");
+                    }
+                    RfncFailed { eid, ret } => {
+                        error!(target: TARGET, "`RFNC` equivalent of legacy call {eid:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    HartIdOutOfRange(hartid) => {
+                        warn!(target: TARGET, "hart {hartid} can't be expressed in legacy hart mask");
+                    }
+                    Shutdown | ShutdownReturned => unreachable!(),
                 }
-                Begin => info!(target: TARGET, "Testing `TIME`"),
-                Pass => info!(target: TARGET, "Sbi `TIME` test pass"),
-                Interval { begin: _, end: _ } => {
-                    info!(
-                        target: TARGET,
-                        "read time register successfuly, set timer +1s"
-                    );
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `Legacy` not exist");
+        }
+        if exts.time.is_available() {
+            time::test(self.delay, |case| {
+                use time::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `TIME`"),
+                    Pass => info!(target: TARGET, "Sbi `TIME` test pass"),
+                    Interval { begin: _, end: _ } => {
+                        info!(
+                            target: TARGET,
+                            "read time register successfuly, set timer +1s"
+                        );
+                    }
+                    ReadFailed => {
+                        error!(target: TARGET, "csrr time failed");
+                        result = false;
+                    }
+                    TimeDecreased { a, b } => {
+                        error!(target: TARGET, "time decreased: {a} -> {b}");
+                        result = false;
+                    }
+                    SetTimer => {
+                        info!(target: TARGET, "timer interrupt delegate successfuly");
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at supervisor timer, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            error!(target: TARGET, "Sbi `TIME` not exist");
+            result = false;
+        }
+        if exts.spi.is_available() {
+            spi::test(self.hartid, |case| {
+                use spi::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `sPI`"),
+                    Pass => info!(target: TARGET, "Sbi `sPI` test pass"),
+                    SendIpi => info!(target: TARGET, "send ipi successfuly"),
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at supervisor soft, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            error!(target: TARGET, "Sbi `sPI` not exist");
+            result = false;
+        }
+        if exts.rfnc.is_available() {
+            rfnc::test(self.hartid, self.hart_mask, self.hart_mask_base, |case| {
+                use rfnc::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `RFNC`"),
+                    Pass => info!(target: TARGET, "Sbi `RFNC` test pass"),
+                    RemoteFenceI(mask) => debug!(target: TARGET, "remote fence.i on {mask:?}"),
+                    RemoteFenceIFailed { hart_mask, ret } => {
+                        error!(target: TARGET, "remote fence.i on {hart_mask:?} failed: {ret:?}");
+                        result = false;
+                    }
+                    RemoteSfenceVma(mask) => {
+                        debug!(target: TARGET, "remote sfence.vma on {mask:?}")
+                    }
+                    RemoteSfenceVmaFailed { hart_mask, ret } => {
+                        error!(target: TARGET, "remote sfence.vma on {hart_mask:?} failed: {ret:?}");
+                        result = false;
+                    }
+                    RemoteSfenceVmaAsid(mask) => {
+                        debug!(target: TARGET, "remote sfence.vma with asid on {mask:?}")
+                    }
+                    RemoteSfenceVmaAsidFailed { hart_mask, ret } => {
+                        error!(
+                            target: TARGET,
+                            "remote sfence.vma with asid on {hart_mask:?} failed: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    HartStartedBeforeTest(id) => warn!(target: TARGET, "hart {id} already started"),
+                    NoStoppedHart => warn!(target: TARGET, "no stopped hart for remote fences"),
+                    BatchBegin(batch) => info!(target: TARGET, "Testing harts: {batch:?}"),
+                    HartStartFailed { hartid, ret } => {
+                        error!(target: TARGET, "hart {hartid} start failed: {ret:?}");
+                        result = false;
+                    }
+                    BatchPass(batch) => info!(target: TARGET, "Testing Pass: {batch:?}"),
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `RFNC` not exist");
+        }
+        if exts.hsm.is_available() {
+            hsm::test(self.hartid, self.hart_mask, self.hart_mask_base, |case| {
+                use hsm::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `HSM`"),
+                    Pass => info!(target: TARGET, "Sbi `HSM` test pass"),
+                    HartStartedBeforeTest(id) => warn!(target: TARGET, "hart {id} already started"),
+                    NoStoppedHart => warn!(target: TARGET, "no stopped hart"),
+                    BatchBegin(batch) => info!(target: TARGET, "Testing harts: {batch:?}"),
+                    HartStarted(id) => debug!(target: TARGET, "hart {id} started"),
+                    HartStartFailed { hartid, ret } => {
+                        error!(target: TARGET, "hart {hartid} start failed: {ret:?}");
+                        result = false;
+                    }
+                    HartSuspendedNonretentive(id) => {
+                        debug!(target: TARGET, "hart {id} suspended nonretentive")
+                    }
+                    HartResumed(id) => debug!(target: TARGET, "hart {id} resumed"),
+                    HartSuspendedRetentive(id) => {
+                        debug!(target: TARGET, "hart {id} suspended retentive")
+                    }
+                    HartStopped(id) => debug!(target: TARGET, "hart {id} stopped"),
+                    BatchPass(batch) => info!(target: TARGET, "Testing Pass: {batch:?}"),
+                }
+            });
+        } else {
+            error!(target: TARGET, "Sbi `HSM` not exist");
+            result = false;
+        }
+        if exts.srst.is_available() {
+            srst::test(|case| {
+                use srst::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `SRST`"),
+                    Pass => info!(target: TARGET, "Sbi `SRST` test pass"),
+                    ResetTypeRejected(ty) => {
+                        debug!(target: TARGET, "reserved reset type {ty:#x} rejected")
+                    }
+                    InvalidParamNotRejected {
+                        reset_type,
+                        reset_reason,
+                        ret,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "system reset ({reset_type:#x}, {reset_reason:#x}) not rejected: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    Reboot(reboot) => info!(target: TARGET, "{reboot:?} reboot"),
+                    RebootFailed { reboot, ret } => {
+                        error!(target: TARGET, "{reboot:?} reboot failed: {ret:?}");
+                        result = false;
+                    }
+                    RebootObserved(reboot) => info!(target: TARGET, "{reboot:?} reboot observed"),
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `SRST` not exist");
+        }
+        if exts.pmu.is_available() {
+            pmu::test(self.hartid, self.delay, |case| {
+                use pmu::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `PMU`"),
+                    Pass => info!(target: TARGET, "Sbi `PMU` test pass"),
+                    NumCounters(num) => info!(target: TARGET, "number of counters = {num}"),
+                    CounterInfo { idx, info } => debug!(target: TARGET, "counter {idx}: {info:?}"),
+                    GetInfoFailed { idx, ret } => {
+                        error!(target: TARGET, "get info of counter {idx} failed: {ret:?}");
+                        result = false;
+                    }
+                    InvalidCounterInfo { idx, raw } => {
+                        error!(target: TARGET, "invalid info of counter {idx}: {raw:#x}");
+                        result = false;
+                    }
+                    EventNotSupported(event) => {
+                        warn!(target: TARGET, "event {event:#x} not supported")
+                    }
+                    ConfigMatchingFailed { event_idx, ret } => {
+                        error!(target: TARGET, "config counter for event {event_idx:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    CounterStartFailed { idx, ret } => {
+                        error!(target: TARGET, "start counter {idx} failed: {ret:?}");
+                        result = false;
+                    }
+                    CounterStopFailed { idx, ret } => {
+                        error!(target: TARGET, "stop counter {idx} failed: {ret:?}");
+                        result = false;
+                    }
+                    AlreadyStartedNotReported { idx, ret } => {
+                        error!(target: TARGET, "restart counter {idx} not rejected: {ret:?}");
+                        result = false;
+                    }
+                    AlreadyStoppedNotReported { idx, ret } => {
+                        error!(target: TARGET, "restop counter {idx} not rejected: {ret:?}");
+                        result = false;
+                    }
+                    CounterReadFailed { idx, csr } => {
+                        error!(target: TARGET, "read counter {idx} at csr {csr:#x} failed");
+                        result = false;
+                    }
+                    CounterNotGrowing { event_idx, values } => {
+                        error!(target: TARGET, "counter for event {event_idx:#x} not growing: {values:?}");
+                        result = false;
+                    }
+                    HardwareEvent {
+                        event_idx,
+                        idx,
+                        values,
+                    } => {
+                        info!(target: TARGET, "counter {idx} for event {event_idx:#x}: {values:?}");
+                    }
+                    FirmwareReadFailed { idx, ret } => {
+                        error!(target: TARGET, "read firmware counter {idx} failed: {ret:?}");
+                        result = false;
+                    }
+                    FirmwareCountMismatch {
+                        event_idx,
+                        expected,
+                        actual,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "firmware event {event_idx:#x} counted {actual}, but {expected} expected"
+                        );
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at supervisor timer, soft or counter overflow, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    FirmwareEvent {
+                        event_idx,
+                        idx,
+                        count,
+                    } => {
+                        info!(target: TARGET, "counter {idx} for firmware event {event_idx:#x}: {count}");
+                    }
+                    OverflowNotSupported => {
+                        warn!(target: TARGET, "counter overflow interrupt not supported")
+                    }
+                    NoOverflowCounter => {
+                        warn!(target: TARGET, "no programmable counter for overflow interrupt")
+                    }
+                    CounterOverflow { idx } => {
+                        info!(target: TARGET, "counter {idx} overflow interrupt received")
+                    }
+                    SnapshotNotSupported => warn!(target: TARGET, "snapshot not supported"),
+                    SnapshotSetShmemFailed(ret) => {
+                        error!(target: TARGET, "set snapshot shared memory failed: {ret:?}");
+                        result = false;
+                    }
+                    SnapshotMisalignedNotRejected(ret) => {
+                        error!(target: TARGET, "misaligned snapshot memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SnapshotInvalidAddressNotRejected(ret) => {
+                        error!(target: TARGET, "invalid snapshot memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SnapshotIndexOutOfRange(idx) => {
+                        error!(target: TARGET, "counter {idx} is out of snapshot range");
+                        result = false;
+                    }
+                    SnapshotMismatch {
+                        idx,
+                        snapshot,
+                        direct,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "snapshot of counter {idx} is {snapshot:#x}, but {direct:#x} read"
+                        );
+                        result = false;
+                    }
+                    Snapshot { idx, value } => {
+                        info!(target: TARGET, "snapshot of counter {idx}: {value:#x}")
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `PMU` not exist");
+        }
+        if exts.dbcn.is_available() {
+            dbcn::test(|case| {
+                use dbcn::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `DBCN`"),
+                    Pass => info!(target: TARGET, "Sbi `DBCN` test pass"),
+                    WriteByte => {}
+                    WritingByteFailed(ret) => {
+                        error!(target: TARGET, "writing byte failed: {ret:?}");
+                        result = false;
+                    }
+                    WriteSlice => info!(target: TARGET, "writing slice successfuly"),
+                    WritingPartialSlice(len) => {
+                        warn!(target: TARGET, "writing partial slice: {len} bytes written");
+                    }
+                    WritingSliceFailed(ret) => {
+                        error!(target: TARGET, "writing slice failed: {ret:?}");
+                        result = false;
+                    }
+                    Read(len) => info!(target: TARGET, "reading {len} bytes from console"),
+                    ReadingFailed(ret) => {
+                        error!(target: TARGET, "reading failed: {ret:?}");
+                        result = false;
+                    }
                 }
-                ReadFailed => {
-                    error!(target: TARGET, "csrr time failed");
+            });
+        } else {
+            error!(target: TARGET, "Sbi `DBCN` not exist");
+            result = false;
+        }
+        if exts.susp.is_available() {
+            susp::test(
+                self.hartid,
+                self.hart_mask,
+                self.hart_mask_base,
+                self.delay,
+                |case| {
+                    use susp::Case::*;
+                    match case {
+                        NotExist => unreachable!(),
+                        Begin => info!(target: TARGET, "Testing `SUSP`"),
+                        Pass => info!(target: TARGET, "Sbi `SUSP` test pass"),
+                        SleepTypeRejected(ty) => {
+                            debug!(target: TARGET, "reserved sleep type {ty:#x} rejected")
+                        }
+                        SleepTypeNotRejected { sleep_type, ret } => {
+                            error!(target: TARGET, "reserved sleep type {sleep_type:#x} not rejected: {ret:?}");
+                            result = false;
+                        }
+                        HsmNotExist => {
+                            warn!(target: TARGET, "Sbi `HSM` not exist, skip suspend to RAM")
+                        }
+                        HartNotStopped(id) => {
+                            warn!(target: TARGET, "hart {id} not stopped, skip suspend to RAM")
+                        }
+                        SuspendFailed(ret) => {
+                            error!(target: TARGET, "suspend to RAM failed: {ret:?}");
+                            result = false;
+                        }
+                        Resumed => info!(target: TARGET, "resumed from suspend to RAM"),
+                        ResumeMismatch { hartid, opaque } => {
+                            error!(
+                                target: TARGET,
+                                "resumed with unexpected hartid {hartid} or opaque {opaque:#x}"
+                            );
+                            result = false;
+                        }
+                    }
+                },
+            );
+        } else {
+            warn!(target: TARGET, "Sbi `SUSP` not exist");
+        }
+        if exts.cppc.is_available() {
+            cppc::test(|case| {
+                use cppc::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `CPPC`"),
+                    Pass => info!(target: TARGET, "Sbi `CPPC` test pass"),
+                    Probe { reg_id, width: 0 } => {
+                        debug!(target: TARGET, "cppc register {reg_id:#x} not implemented")
+                    }
+                    Probe { reg_id, width } => {
+                        debug!(target: TARGET, "cppc register {reg_id:#x} is {width}-bit")
+                    }
+                    ProbeFailed { reg_id, ret } => {
+                        error!(target: TARGET, "probe cppc register {reg_id:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    ReservedNotRejected { reg_id, ret } => {
+                        error!(target: TARGET, "reserved cppc register {reg_id:#x} not rejected: {ret:?}");
+                        result = false;
+                    }
+                    Read { reg_id, value } => {
+                        info!(target: TARGET, "cppc register {reg_id:#x} = {value:#x}")
+                    }
+                    ReadFailed { reg_id, ret } => {
+                        error!(target: TARGET, "read cppc register {reg_id:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    ReadOnlyWriteNotDenied { reg_id, ret } => {
+                        error!(
+                            target: TARGET,
+                            "write read-only cppc register {reg_id:#x} not denied: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    WriteDesired {
+                        value,
+                        lowest,
+                        highest,
+                    } => {
+                        info!(
+                            target: TARGET,
+                            "desired performance {value} written in [{lowest}, {highest}]"
+                        );
+                    }
+                    WriteFailed { reg_id, ret } => {
+                        error!(target: TARGET, "write cppc register {reg_id:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `CPPC` not exist");
+        }
+        if exts.nacl.is_available() {
+            nacl::test(|case| {
+                use nacl::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `NACL`"),
+                    Pass => info!(target: TARGET, "Sbi `NACL` test pass"),
+                    Feature {
+                        feature_id,
+                        available,
+                    } => {
+                        debug!(target: TARGET, "feature {feature_id} available = {available}");
+                    }
+                    UnknownFeatureAvailable(feature_id) => {
+                        error!(target: TARGET, "undefined feature {feature_id:#x} reported as available");
+                        result = false;
+                    }
+                    UnsupportedNotRejected { feature_id, ret } => {
+                        error!(
+                            target: TARGET,
+                            "calling unavailable feature {feature_id} not rejected: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    MisalignedNotRejected(ret) => {
+                        error!(target: TARGET, "misaligned nested acceleration memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    FlagsNotRejected(ret) => {
+                        error!(target: TARGET, "non-zero flags not rejected: {ret:?}");
+                        result = false;
+                    }
+                    InvalidAddressNotRejected(ret) => {
+                        error!(target: TARGET, "invalid shared memory address not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SetShmemFailed(ret) => {
+                        error!(target: TARGET, "set nested acceleration shared memory failed: {ret:?}");
+                        result = false;
+                    }
+                    NoShmemNotRejected(ret) => {
+                        error!(target: TARGET, "synchronizing CSRs without shared memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    InvalidCsrNotRejected(ret) => {
+                        error!(target: TARGET, "synchronizing non H-extension CSR not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SyncCsrFailed { csr_num, ret } => {
+                        error!(target: TARGET, "synchronize CSR {csr_num:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    SyncCsr { csr_num, value } => {
+                        info!(target: TARGET, "CSR {csr_num:#x} synchronized with {value:#x}");
+                    }
+                    CsrMismatch {
+                        csr_num,
+                        expected,
+                        actual,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "CSR {csr_num:#x} expected {expected:#x}, but {actual:#x} was synchronized"
+                        );
+                        result = false;
+                    }
+                    DirtyNotCleared(csr_num) => {
+                        error!(target: TARGET, "dirty bit of CSR {csr_num:#x} not cleared after synchronized");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `NACL` not exist");
+        }
+        if exts.sta.is_available() {
+            sta::test(self.delay, |case| {
+                use sta::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `STA`"),
+                    Pass => info!(target: TARGET, "Sbi `STA` test pass"),
+                    MisalignedNotRejected(ret) => {
+                        error!(target: TARGET, "misaligned steal-time memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SetShmemFailed(ret) => {
+                        error!(target: TARGET, "set steal-time shared memory failed: {ret:?}");
+                        result = false;
+                    }
+                    SequenceOdd(sequence) => {
+                        error!(target: TARGET, "steal-time sequence stays odd: {sequence}");
+                        result = false;
+                    }
+                    StealTime {
+                        sequence,
+                        steal,
+                        preempted,
+                    } => {
+                        info!(
+                            target: TARGET,
+                            "steal time = {steal}ns, preempted = {preempted}, sequence = {sequence}"
+                        );
+                    }
+                    SequenceNotAdvanced(sequence) => {
+                        error!(target: TARGET, "steal-time sequence not advanced: {sequence}");
+                        result = false;
+                    }
+                    StealDecreased { a, b } => {
+                        error!(target: TARGET, "steal time decreased: {a} -> {b}");
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at supervisor timer, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    UpdatedAfterDisable => {
+                        error!(target: TARGET, "steal-time record updated after disabled");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `STA` not exist");
+        }
+        if exts.sse.is_available() {
+            sse::test(self.hartid, |case| {
+                use sse::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `SSE`"),
+                    Pass => info!(target: TARGET, "Sbi `SSE` test pass"),
+                    AttrCountNotRejected(ret) => {
+                        error!(target: TARGET, "reading zero attributes not rejected: {ret:?}");
+                        result = false;
+                    }
+                    UnregisterNotRejected(ret) => {
+                        error!(target: TARGET, "unregistering unused event not rejected: {ret:?}");
+                        result = false;
+                    }
+                    MisalignedNotRejected(ret) => {
+                        error!(target: TARGET, "misaligned handler entry not rejected: {ret:?}");
+                        result = false;
+                    }
+                    RegisterFailed(ret) => {
+                        error!(target: TARGET, "register event failed: {ret:?}");
+                        result = false;
+                    }
+                    DoubleRegisterNotRejected(ret) => {
+                        error!(target: TARGET, "registering event twice not rejected: {ret:?}");
+                        result = false;
+                    }
+                    ReadAttrsFailed { attr_id, ret } => {
+                        error!(target: TARGET, "read attribute {attr_id:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    WriteAttrsFailed { attr_id, ret } => {
+                        error!(target: TARGET, "write attribute {attr_id:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    ReadOnlyWriteNotRejected(ret) => {
+                        error!(target: TARGET, "writing read-only status attribute not rejected: {ret:?}");
+                        result = false;
+                    }
+                    AttrMismatch {
+                        attr_id,
+                        expected,
+                        actual,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "attribute {attr_id:#x} expected {expected:#x}, but {actual:#x} was read"
+                        );
+                        result = false;
+                    }
+                    EnableFailed(ret) => {
+                        error!(target: TARGET, "enable event failed: {ret:?}");
+                        result = false;
+                    }
+                    UnmaskFailed(ret) => {
+                        error!(target: TARGET, "unmask events on current hart failed: {ret:?}");
+                        result = false;
+                    }
+                    InjectFailed(ret) => {
+                        error!(target: TARGET, "inject event failed: {ret:?}");
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at illegal instruction after event completed, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    HandlerNotCalled => {
+                        error!(target: TARGET, "event handler not called after injected");
+                        result = false;
+                    }
+                    HandlerMismatch { hartid, arg } => {
+                        error!(
+                            target: TARGET,
+                            "event handler called with hartid = {hartid}, arg = {arg:#x}"
+                        );
+                        result = false;
+                    }
+                    Handled { interrupted_pc } => {
+                        info!(target: TARGET, "event handled, interrupted at {interrupted_pc:#x}");
+                    }
+                    CompleteFailed => {
+                        error!(target: TARGET, "complete event did not return to interrupted context");
+                        result = false;
+                    }
+                    ResumeMismatch { a6, a7 } => {
+                        error!(
+                            target: TARGET,
+                            "interrupted registers not restored: a6 = {a6:#x}, a7 = {a7:#x}"
+                        );
+                        result = false;
+                    }
+                    DisableFailed(ret) => {
+                        error!(target: TARGET, "disable event failed: {ret:?}");
+                        result = false;
+                    }
+                    UnregisterFailed(ret) => {
+                        error!(target: TARGET, "unregister event failed: {ret:?}");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `SSE` not exist");
+        }
+        if exts.fwft.is_available() {
+            fwft::test(|case| {
+                use fwft::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `FWFT`"),
+                    Pass => info!(target: TARGET, "Sbi `FWFT` test pass"),
+                    ReservedNotRejected { feature, ret } => {
+                        error!(target: TARGET, "reserved feature {feature:#x} not rejected: {ret:?}");
+                        result = false;
+                    }
+                    NotSupported(feature) => {
+                        warn!(target: TARGET, "feature {feature:#x} not supported");
+                    }
+                    Get { feature, value } => {
+                        debug!(target: TARGET, "feature {feature:#x} = {value}");
+                    }
+                    GetFailed { feature, ret } => {
+                        error!(target: TARGET, "get feature {feature:#x} failed: {ret:?}");
+                        result = false;
+                    }
+                    SetFailed {
+                        feature,
+                        value,
+                        ret,
+                    } => {
+                        error!(target: TARGET, "set feature {feature:#x} to {value} failed: {ret:?}");
+                        result = false;
+                    }
+                    ValueMismatch {
+                        feature,
+                        expected,
+                        actual,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "feature {feature:#x} expected {expected}, but {actual} was read"
+                        );
+                        result = false;
+                    }
+                    InvalidParamNotRejected {
+                        feature,
+                        value,
+                        flags,
+                        ret,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "set feature {feature:#x} with value = {value}, flags = {flags:#x} not rejected: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    MisalignedDelegated => {
+                        info!(target: TARGET, "misaligned load trapped into S-mode with delegation")
+                    }
+                    MisalignedNotTrapped => {
+                        warn!(target: TARGET, "misaligned load not trapped, hardware supports misaligned access")
+                    }
+                    MisalignedNotDelegated => {
+                        error!(target: TARGET, "misaligned load trapped into S-mode without delegation");
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at misaligned load or illegal instruction, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    Locked(feature) => {
+                        warn!(target: TARGET, "feature {feature:#x} is locked until reset");
+                    }
+                    LockedSetNotDenied { feature, ret } => {
+                        error!(target: TARGET, "set locked feature {feature:#x} not denied: {ret:?}");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `FWFT` not exist");
+        }
+        if exts.dbtr.is_available() {
+            dbtr::test(|case| {
+                use dbtr::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `DBTR`"),
+                    Pass => info!(target: TARGET, "Sbi `DBTR` test pass"),
+                    NumTriggers { ttype, count } => {
+                        debug!(target: TARGET, "{count} trigger(s) of type {ttype}");
+                    }
+                    NoAddressMatchTrigger => {
+                        warn!(target: TARGET, "no address match trigger, skip execute trigger test");
+                    }
+                    NoShmemNotRejected(ret) => {
+                        error!(target: TARGET, "installing trigger without shared memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SetShmemFailed(ret) => {
+                        error!(target: TARGET, "set trigger shared memory failed: {ret:?}");
+                        result = false;
+                    }
+                    InstallFailed(ret) => {
+                        error!(target: TARGET, "install execute trigger failed: {ret:?}");
+                        result = false;
+                    }
+                    Installed { idx, tdata1 } => {
+                        info!(target: TARGET, "execute trigger installed at {idx} with tdata1 = {tdata1:#x}");
+                    }
+                    ReadFailed(ret) => {
+                        error!(target: TARGET, "read installed trigger failed: {ret:?}");
+                        result = false;
+                    }
+                    AddressMismatch { expected, actual } => {
+                        error!(
+                            target: TARGET,
+                            "trigger address expected {expected:#x}, but {actual:#x} was read"
+                        );
+                        result = false;
+                    }
+                    Breakpoint => info!(target: TARGET, "breakpoint trapped at target function"),
+                    BreakpointMismatch { expected, sepc } => {
+                        error!(
+                            target: TARGET,
+                            "breakpoint expected at {expected:#x}, but trapped at {sepc:#x}"
+                        );
+                        result = false;
+                    }
+                    UnexpectedTrap(trap) => {
+                        error!(
+                            target: TARGET,
+                            "expect trap at breakpoint or illegal instruction, but {trap:?} was caught"
+                        );
+                        result = false;
+                    }
+                    UninstallFailed(ret) => {
+                        error!(target: TARGET, "uninstall execute trigger failed: {ret:?}");
+                        result = false;
+                    }
+                    TriggeredAfterUninstall => {
+                        error!(target: TARGET, "breakpoint trapped after trigger uninstalled");
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `DBTR` not exist");
+        }
+        if exts.mpxy.is_available() {
+            mpxy::test(None, |case| {
+                use mpxy::Case::*;
+                match case {
+                    NotExist => unreachable!(),
+                    Begin => info!(target: TARGET, "Testing `MPXY`"),
+                    Pass => info!(target: TARGET, "Sbi `MPXY` test pass"),
+                    ShmemSize(size) => {
+                        debug!(target: TARGET, "message proxy shared memory size = {size}");
+                    }
+                    ShmemTooLarge(size) => {
+                        warn!(target: TARGET, "shared memory of {size} bytes too large, skip message proxy test");
+                    }
+                    NoShmemNotRejected(ret) => {
+                        error!(target: TARGET, "getting channel IDs without shared memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    MisalignedNotRejected(ret) => {
+                        error!(target: TARGET, "misaligned message proxy memory not rejected: {ret:?}");
+                        result = false;
+                    }
+                    FlagsNotRejected(ret) => {
+                        error!(target: TARGET, "reserved flags not rejected: {ret:?}");
+                        result = false;
+                    }
+                    SetShmemFailed(ret) => {
+                        error!(target: TARGET, "set message proxy shared memory failed: {ret:?}");
+                        result = false;
+                    }
+                    GetChannelIdsFailed(ret) => {
+                        error!(target: TARGET, "get channel IDs failed: {ret:?}");
+                        result = false;
+                    }
+                    Channel {
+                        channel_id,
+                        protocol_id,
+                        protocol_version,
+                        msg_max_len,
+                    } => {
+                        info!(
+                            target: TARGET,
+                            "channel {channel_id}: protocol {protocol_id:#x} version {protocol_version:#x}, max message length = {msg_max_len}"
+                        );
+                    }
+                    ReadAttributesFailed { channel_id, ret } => {
+                        error!(target: TARGET, "read attributes of channel {channel_id} failed: {ret:?}");
+                        result = false;
+                    }
+                    InvalidChannelNotRejected { channel_id, ret } => {
+                        error!(target: TARGET, "invalid channel {channel_id} not rejected: {ret:?}");
+                        result = false;
+                    }
+                    TooBigNotRejected {
+                        channel_id,
+                        len,
+                        ret,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "message of {len} bytes on channel {channel_id} not rejected: {ret:?}"
+                        );
+                        result = false;
+                    }
+                    Response {
+                        channel_id,
+                        message_id,
+                        len,
+                    } => {
+                        info!(
+                            target: TARGET,
+                            "message {message_id:#x} on channel {channel_id} responded with {len} bytes"
+                        );
+                    }
+                    Sent {
+                        channel_id,
+                        message_id,
+                    } => {
+                        info!(target: TARGET, "message {message_id:#x} sent on channel {channel_id}");
+                    }
+                    MessageTooLarge {
+                        channel_id,
+                        message_id,
+                        len,
+                        size,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "message {message_id:#x} on channel {channel_id} of {len} bytes is larger than shared memory of {size} bytes"
+                        );
+                        result = false;
+                    }
+                    SendFailed {
+                        channel_id,
+                        message_id,
+                        with_response,
+                        ret,
+                    } => {
+                        error!(
+                            target: TARGET,
+                            "send message {message_id:#x} on channel {channel_id} (with response = {with_response}) failed: {ret:?}"
+                        );
+                        result = false;
+                    }
+                }
+            });
+        } else {
+            warn!(target: TARGET, "Sbi `MPXY` not exist");
+        }
+        negative::test(|case| {
+            use negative::Case::*;
+            match case {
+                Begin => info!(target: TARGET, "Testing unsupported calls"),
+                Pass => info!(target: TARGET, "Unsupported calls test pass"),
+                Rejected { eid, fid } => {
+                    debug!(target: TARGET, "call {eid:#x}:{fid:#x} rejected as not supported");
+                }
+                NotRejected { eid, fid, ret } => {
+                    error!(target: TARGET, "unsupported call {eid:#x}:{fid:#x} not rejected: {ret:?}");
                     result = false;
                 }
-                TimeDecreased { a, b } => {
-                    error!(target: TARGET, "time decreased: {a} -> {b}");
+                Trapped { eid, fid, trap } => {
+                    error!(target: TARGET, "unsupported call {eid:#x}:{fid:#x} trapped: {trap:?}");
                     result = false;
                 }
-                SetTimer => {
-                    info!(target: TARGET, "timer interrupt delegate successfuly");
+            }
+        });
+        gpr::test(self.hartid, |case| {
+            use gpr::Case::*;
+            match case {
+                Begin => info!(target: TARGET, "Testing general purpose register preservation"),
+                Pass => info!(target: TARGET, "General purpose register preservation test pass"),
+                Preserved { eid, fid } => {
+                    debug!(target: TARGET, "call {eid:#x}:{fid:#x} preserved registers");
                 }
-                UnexpectedTrap(trap) => {
+                Clobbered {
+                    eid,
+                    fid,
+                    register,
+                    expected,
+                    actual,
+                } => {
                     error!(
                         target: TARGET,
-                        "expect trap at supervisor timer, but {trap:?} was caught"
+                        "call {eid:#x}:{fid:#x} clobbered {register}: expected {expected:#x}, got {actual:#x}"
                     );
                     result = false;
                 }
+                Trapped { eid, fid, trap } => {
+                    error!(target: TARGET, "call {eid:#x}:{fid:#x} trapped: {trap:?}");
+                    result = false;
+                }
             }
         });
-        spi::test(self.hartid, |case| {
-            use spi::Case::*;
+        fpvec::test(self.hartid, |case| {
+            use fpvec::Case::*;
             match case {
                 NotExist => {
-                    error!(target: TARGET, "Sbi `sPI` not exist");
-                    result = false;
+                    warn!(target: TARGET, "Neither floating-point nor vector unit is detected")
+                }
+                Begin => {
+                    info!(target: TARGET, "Testing floating-point and vector state preservation")
+                }
+                Pass => {
+                    info!(target: TARGET, "Floating-point and vector state preservation test pass")
+                }
+                Units { float, vlenb } => {
+                    info!(target: TARGET, "floating-point unit: {float}, vector register bytes: {vlenb:?}");
                 }
-                Begin => info!(target: TARGET, "Testing `sPI`"),
-                Pass => info!(target: TARGET, "Sbi `sPI` test pass"),
-                SendIpi => info!(target: TARGET, "send ipi successfuly"),
-                UnexpectedTrap(trap) => {
+                VectorTooLong(vlenb) => {
+                    warn!(target: TARGET, "vector registers of {vlenb} bytes are too long to be tested, skipped");
+                }
+                Preserved { eid, fid } => {
+                    debug!(target: TARGET, "call {eid:#x}:{fid:#x} preserved floating-point and vector state");
+                }
+                StatusChanged {
+                    eid,
+                    fid,
+                    field,
+                    expected,
+                    actual,
+                } => {
                     error!(
                         target: TARGET,
-                        "expect trap at supervisor soft, but {trap:?} was caught"
+                        "call {eid:#x}:{fid:#x} changed sstatus.{field}: expected {expected:#b}, got {actual:#b}"
                     );
                     result = false;
                 }
-            }
-        });
-        hsm::test(self.hartid, self.hart_mask, self.hart_mask_base, |case| {
-            use hsm::Case::*;
-            match case {
-                NotExist => {
-                    error!(target: TARGET, "Sbi `HSM` not exist");
+                FloatClobbered {
+                    eid,
+                    fid,
+                    register,
+                    expected,
+                    actual,
+                } => {
+                    error!(
+                        target: TARGET,
+                        "call {eid:#x}:{fid:#x} clobbered f{register}: expected {expected:#x}, got {actual:#x}"
+                    );
                     result = false;
                 }
-                Begin => info!(target: TARGET, "Testing `HSM`"),
-                Pass => info!(target: TARGET, "Sbi `HSM` test pass"),
-                HartStartedBeforeTest(id) => warn!(target: TARGET, "hart {id} already started"),
-                NoStoppedHart => warn!(target: TARGET, "no stopped hart"),
-                BatchBegin(batch) => info!(target: TARGET, "Testing harts: {batch:?}"),
-                HartStarted(id) => debug!(target: TARGET, "hart {id} started"),
-                HartStartFailed { hartid, ret } => {
-                    error!(target: TARGET, "hart {hartid} start failed: {ret:?}");
+                VectorClobbered { eid, fid, register } => {
+                    error!(target: TARGET, "call {eid:#x}:{fid:#x} clobbered v{register}");
                     result = false;
                 }
-                HartSuspendedNonretentive(id) => {
-                    debug!(target: TARGET, "hart {id} suspended nonretentive")
-                }
-                HartResumed(id) => debug!(target: TARGET, "hart {id} resumed"),
-                HartSuspendedRetentive(id) => {
-                    debug!(target: TARGET, "hart {id} suspended retentive")
+                CsrClobbered {
+                    eid,
+                    fid,
+                    csr,
+                    expected,
+                    actual,
+                } => {
+                    error!(
+                        target: TARGET,
+                        "call {eid:#x}:{fid:#x} clobbered {csr}: expected {expected:#x}, got {actual:#x}"
+                    );
+                    result = false;
                 }
-                HartStopped(id) => debug!(target: TARGET, "hart {id} stopped"),
-                BatchPass(batch) => info!(target: TARGET, "Testing Pass: {batch:?}"),
             }
         });
-        dbcn::test(|case| {
-            use dbcn::Case::*;
+        scsr::test(self.hartid, |case| {
+            use scsr::Case::*;
             match case {
-                NotExist => {
-                    error!(target: TARGET, "Sbi `DBCN` not exist");
-                    result = false;
-                }
-                Begin => info!(target: TARGET, "Testing `DBCN`"),
-                Pass => info!(target: TARGET, "Sbi `DBCN` test pass"),
-                WriteByte => {}
-                WritingByteFailed(ret) => {
-                    error!(target: TARGET, "writing byte failed: {ret:?}");
-                    result = false;
+                Begin => info!(target: TARGET, "Testing supervisor CSR preservation"),
+                Pass => info!(target: TARGET, "Supervisor CSR preservation test pass"),
+                Skipped(csr) => {
+                    warn!(target: TARGET, "{csr} is not implemented or not writable, skipped")
                 }
-                WriteSlice => info!(target: TARGET, "writing slice successfuly"),
-                WritingPartialSlice(len) => {
-                    warn!(target: TARGET, "writing partial slice: {len} bytes written");
+                Preserved { eid, fid } => {
+                    debug!(target: TARGET, "call {eid:#x}:{fid:#x} preserved supervisor CSRs");
                 }
-                WritingSliceFailed(ret) => {
-                    error!(target: TARGET, "writing slice failed: {ret:?}");
+                Clobbered {
+                    eid,
+                    fid,
+                    csr,
+                    expected,
+                    actual,
+                } => {
+                    error!(
+                        target: TARGET,
+                        "call {eid:#x}:{fid:#x} modified {csr}: expected {expected:#x}, got {actual:#x}"
+                    );
                     result = false;
                 }
-                Read(len) => info!(target: TARGET, "reading {len} bytes from console"),
-                ReadingFailed(ret) => {
-                    error!(target: TARGET, "reading failed: {ret:?}");
+                TooManyCalls => {
+                    error!(target: TARGET, "too many calls to be recorded");
                     result = false;
                 }
             }
         });
         result
     }
+
+    /// Start testing process, then shut down the system reporting the verdict to the host.
+    ///
+    /// The verdict is carried by system reset reason, so the host like QEMU
+    /// could exit with corresponding status without scraping console output.
+    pub fn test_and_shutdown(self) -> ! {
+        let pass = self.test();
+        srst::shutdown(pass, |case| {
+            use srst::ShutdownCase::*;
+            match case {
+                NotExist => warn!(target: TARGET, "Sbi `SRST` not exist, can't report verdict"),
+                Shutdown { pass: true } => info!(target: TARGET, "Shutdown with test passed"),
+                Shutdown { pass: false } => error!(target: TARGET, "Shutdown with test failed"),
+                ShutdownFailed(ret) => error!(target: TARGET, "shutdown failed: {ret:?}"),
+                LegacyShutdown => warn!(target: TARGET, "Shutdown through legacy extension"),
+                Park => error!(target: TARGET, "no shutdown method available, parking hart"),
+            }
+        })
+    }
 }
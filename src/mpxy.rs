@@ -0,0 +1,353 @@
+//! Message proxy extension test suite.
+
+use crate::binary::{sbi_call_0, sbi_call_1, sbi_call_3};
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// Message proxy extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for message proxy extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for size of shared memory required by the implementation.
+    ShmemSize(usize),
+    /// Test is skipped for shared memory required by the implementation is larger than provided.
+    ShmemTooLarge(usize),
+    /// Test failed for getting channel IDs without shared memory is not rejected with `SBI_ERR_NO_SHMEM`.
+    NoShmemNotRejected(SbiRet),
+    /// Test failed for misaligned shared memory is not rejected with `SBI_ERR_INVALID_PARAM`.
+    MisalignedNotRejected(SbiRet),
+    /// Test failed for reserved flags is not rejected with `SBI_ERR_INVALID_PARAM`.
+    FlagsNotRejected(SbiRet),
+    /// Test failed for can't set shared memory with [`SbiRet`] error.
+    SetShmemFailed(SbiRet),
+    /// Test failed for can't get channel IDs with [`SbiRet`] error.
+    GetChannelIdsFailed(SbiRet),
+    /// Test process for standard attributes of a channel have been read.
+    Channel {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message protocol ID.
+        protocol_id: u32,
+        /// The message protocol version.
+        protocol_version: u32,
+        /// Maximum message data length in bytes.
+        msg_max_len: u32,
+    },
+    /// Test failed for can't read channel attributes with [`SbiRet`] error.
+    ReadAttributesFailed {
+        /// The channel ID.
+        channel_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for an invalid channel is not rejected with `SBI_ERR_NOT_SUPPORTED`.
+    InvalidChannelNotRejected {
+        /// The invalid channel ID.
+        channel_id: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for a message longer than maximum length is not rejected with `SBI_ERR_INVALID_PARAM`.
+    TooBigNotRejected {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message data length passed to the SBI call.
+        len: usize,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for a message has been sent and a response has been received.
+    Response {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message ID.
+        message_id: u32,
+        /// Length of response data in bytes.
+        len: usize,
+    },
+    /// Test process for a message has been sent without response.
+    Sent {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message ID.
+        message_id: u32,
+    },
+    /// Test failed for the message data is larger than shared memory size reported by the implementation.
+    MessageTooLarge {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message ID.
+        message_id: u32,
+        /// Length of message data in bytes.
+        len: usize,
+        /// Size of shared memory in bytes.
+        size: usize,
+    },
+    /// Test failed for can't send a message with [`SbiRet`] error.
+    SendFailed {
+        /// The channel ID.
+        channel_id: u32,
+        /// The message ID.
+        message_id: u32,
+        /// Whether the message is sent with response.
+        with_response: bool,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// All test cases on message proxy extension has passed.
+    Pass,
+}
+
+/// A message to be sent during message proxy extension test.
+#[derive(Clone, Copy, Debug)]
+pub struct Message<'a> {
+    /// The channel ID to send message on.
+    pub channel_id: u32,
+    /// The message ID defined by the message protocol of the channel.
+    pub message_id: u32,
+    /// The message data.
+    pub data: &'a [u8],
+}
+
+pub(crate) const EID_MPXY: usize = 0x4d505859;
+const GET_SHMEM_SIZE: usize = 0;
+const SET_SHMEM: usize = 1;
+const GET_CHANNEL_IDS: usize = 2;
+const READ_ATTRIBUTES: usize = 3;
+const SEND_MESSAGE_WITH_RESPONSE: usize = 5;
+const SEND_MESSAGE_WITHOUT_RESPONSE: usize = 6;
+
+/// 标准通道属性的数量。
+const STANDARD_ATTRIBUTES: usize = 0x0c;
+const ATTR_MSG_PROT_ID: usize = 0x00;
+const ATTR_MSG_PROT_VERSION: usize = 0x01;
+const ATTR_MSG_MAX_LEN: usize = 0x02;
+
+/// 保留的共享内存标志。
+const RESERVED_FLAGS: usize = 0b11;
+
+/// 最多记录的通道数量。
+const MAX_CHANNELS: usize = 64;
+
+/// 检查超长消息时使用的消息编号，不属于任何协议定义的消息。
+const UNUSED_MESSAGE_ID: u32 = u32::MAX;
+
+const SHMEM_CAPACITY: usize = 4 * 4096;
+
+#[repr(C, align(4096))]
+struct Shmem([u8; SHMEM_CAPACITY]);
+
+static mut SHMEM: Shmem = Shmem([0; SHMEM_CAPACITY]);
+
+/// Test message proxy extension.
+///
+/// If `message` is provided, it is sent with and without response after
+/// channels are enumerated.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(message: Option<Message>, mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_MPXY).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    let size = sbi_call_0(EID_MPXY, GET_SHMEM_SIZE).value;
+    f(Case::ShmemSize(size));
+    if size > SHMEM_CAPACITY {
+        f(Case::ShmemTooLarge(size));
+        f(Case::Pass);
+        return;
+    }
+    // 未设置共享内存时不能获取通道
+    let ret = set_shmem(usize::MAX, usize::MAX, 0);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let ret = sbi_call_1(EID_MPXY, GET_CHANNEL_IDS, 0);
+    if ret.err() != Some(Error::NoShmem) {
+        f(Case::NoShmemNotRejected(ret));
+        return;
+    }
+    // 检查设置共享内存的错误路径
+    let shmem = core::ptr::addr_of_mut!(SHMEM);
+    let ret = set_shmem(shmem as usize + 8, 0, 0);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::MisalignedNotRejected(ret));
+        return;
+    }
+    let ret = set_shmem(shmem as _, 0, RESERVED_FLAGS);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::FlagsNotRejected(ret));
+        return;
+    }
+    // 设置共享内存
+    let ret = set_shmem(shmem as _, 0, 0);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let mut pass = test_channels(shmem, &mut f);
+    if let (true, Some(message)) = (pass, message) {
+        pass = test_message(shmem, size, message, &mut f);
+    }
+    let ret = set_shmem(usize::MAX, usize::MAX, 0);
+    if pass && ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 枚举通道并读取标准属性，然后检查错误路径。
+fn test_channels(shmem: *mut Shmem, mut f: impl FnMut(Case)) -> bool {
+    let mut ids = [0u32; MAX_CHANNELS];
+    let mut count = 0;
+    let mut start = 0;
+    loop {
+        let ret = sbi_call_1(EID_MPXY, GET_CHANNEL_IDS, start);
+        if ret.is_err() {
+            f(Case::GetChannelIdsFailed(ret));
+            return false;
+        }
+        let remaining = unsafe { word(shmem, 0) };
+        let returned = unsafe { word(shmem, 1) } as usize;
+        for i in 0..returned {
+            if count < MAX_CHANNELS {
+                ids[count] = unsafe { word(shmem, 2 + i) };
+                count += 1;
+            }
+        }
+        start += returned;
+        if remaining == 0 || returned == 0 {
+            break;
+        }
+    }
+    let ids = &ids[..count];
+    for &channel_id in ids {
+        let ret = read_attributes(channel_id, 0, STANDARD_ATTRIBUTES);
+        if ret.is_err() {
+            f(Case::ReadAttributesFailed { channel_id, ret });
+            return false;
+        }
+        let msg_max_len = unsafe { word(shmem, ATTR_MSG_MAX_LEN) };
+        f(Case::Channel {
+            channel_id,
+            protocol_id: unsafe { word(shmem, ATTR_MSG_PROT_ID) },
+            protocol_version: unsafe { word(shmem, ATTR_MSG_PROT_VERSION) },
+            msg_max_len,
+        });
+        // 超出最大长度的消息应在发送前被拒绝；通道可能不支持不需要响应的消息，因此以需要响应的方式发送。
+        // 实现未拒绝时消息会被真正发送，因此只在通道报告了最大长度时检查，并使用协议不会定义的消息编号
+        if msg_max_len == 0 {
+            continue;
+        }
+        let len = (msg_max_len as usize).saturating_add(1);
+        let ret = sbi_call_3(
+            EID_MPXY,
+            SEND_MESSAGE_WITH_RESPONSE,
+            channel_id as _,
+            UNUSED_MESSAGE_ID as _,
+            len,
+        );
+        if ret.err() != Some(Error::InvalidParam) {
+            f(Case::TooBigNotRejected {
+                channel_id,
+                len,
+                ret,
+            });
+            return false;
+        }
+    }
+    // 选择一个不存在的通道
+    let channel_id = (0..=u32::MAX)
+        .rev()
+        .find(|id| !ids.contains(id))
+        .unwrap_or(u32::MAX);
+    let ret = read_attributes(channel_id, 0, 1);
+    if ret.err() != Some(Error::NotSupported) {
+        f(Case::InvalidChannelNotRejected { channel_id, ret });
+        return false;
+    }
+    true
+}
+
+/// 分别以需要响应和不需要响应的方式发送消息，消息不能超过实现报告的共享内存大小。
+fn test_message(shmem: *mut Shmem, size: usize, message: Message, mut f: impl FnMut(Case)) -> bool {
+    let Message {
+        channel_id,
+        message_id,
+        data,
+    } = message;
+    let len = data.len();
+    if len > size {
+        f(Case::MessageTooLarge {
+            channel_id,
+            message_id,
+            len,
+            size,
+        });
+        return false;
+    }
+    for (fid, with_response) in [
+        (SEND_MESSAGE_WITH_RESPONSE, true),
+        (SEND_MESSAGE_WITHOUT_RESPONSE, false),
+    ] {
+        // 响应会覆盖共享内存，每次发送前重新写入消息
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), shmem as *mut u8, len) };
+        let ret = sbi_call_3(EID_MPXY, fid, channel_id as _, message_id as _, len);
+        if ret.is_err() {
+            f(Case::SendFailed {
+                channel_id,
+                message_id,
+                with_response,
+                ret,
+            });
+            return false;
+        }
+        if with_response {
+            f(Case::Response {
+                channel_id,
+                message_id,
+                len: ret.value,
+            });
+        } else {
+            f(Case::Sent {
+                channel_id,
+                message_id,
+            });
+        }
+    }
+    true
+}
+
+/// 读取共享内存中的第 `i` 个 32 位字。
+///
+/// # Safety
+///
+/// `shmem` 必须指向有效的共享内存。
+#[inline]
+unsafe fn word(shmem: *mut Shmem, i: usize) -> u32 {
+    (shmem as *const u32).add(i).read_volatile()
+}
+
+#[inline]
+fn set_shmem(lo: usize, hi: usize, flags: usize) -> SbiRet {
+    sbi_call_3(EID_MPXY, SET_SHMEM, lo, hi, flags)
+}
+
+#[inline]
+fn read_attributes(channel_id: u32, base_attribute_id: usize, attribute_count: usize) -> SbiRet {
+    sbi_call_3(
+        EID_MPXY,
+        READ_ATTRIBUTES,
+        channel_id as _,
+        base_attribute_id,
+        attribute_count,
+    )
+}
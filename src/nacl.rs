@@ -0,0 +1,301 @@
+//! Nested acceleration extension test suite.
+
+use sbi::{SbiRet, SharedPtr};
+use sbi_spec::{
+    binary::Error,
+    nacl::{feature_id, shmem_size, EID_NACL},
+};
+
+/// Nested acceleration extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for nested acceleration extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for a nested acceleration feature has been probed.
+    Feature {
+        /// The feature ID.
+        feature_id: u32,
+        /// Whether the feature is available.
+        available: bool,
+    },
+    /// Test failed for an undefined feature is reported as available.
+    UnknownFeatureAvailable(u32),
+    /// Test failed for calling a function of an unavailable feature is not rejected with `SBI_ERR_NOT_SUPPORTED`.
+    UnsupportedNotRejected {
+        /// The feature ID.
+        feature_id: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for misaligned shared memory is not rejected with `SBI_ERR_INVALID_PARAM`.
+    MisalignedNotRejected(SbiRet),
+    /// Test failed for non-zero flags is not rejected with `SBI_ERR_INVALID_PARAM`.
+    FlagsNotRejected(SbiRet),
+    /// Test failed for unavailable shared memory address is not rejected with `SBI_ERR_INVALID_ADDRESS`.
+    InvalidAddressNotRejected(SbiRet),
+    /// Test failed for can't set shared memory with [`SbiRet`] error.
+    SetShmemFailed(SbiRet),
+    /// Test failed for synchronizing CSRs without shared memory is not rejected with `SBI_ERR_NO_SHMEM`.
+    NoShmemNotRejected(SbiRet),
+    /// Test failed for synchronizing a non H-extension CSR is not rejected with `SBI_ERR_INVALID_PARAM`.
+    InvalidCsrNotRejected(SbiRet),
+    /// Test failed for can't synchronize a CSR with [`SbiRet`] error.
+    SyncCsrFailed {
+        /// The CSR number.
+        csr_num: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for a CSR value has been written and read back through shared memory.
+    SyncCsr {
+        /// The CSR number.
+        csr_num: usize,
+        /// Value of the CSR.
+        value: usize,
+    },
+    /// Test failed for a CSR does not hold the value written through shared memory.
+    CsrMismatch {
+        /// The CSR number.
+        csr_num: usize,
+        /// The value written through shared memory.
+        expected: usize,
+        /// The value read back through shared memory.
+        actual: usize,
+    },
+    /// Test failed for the dirty bit of a CSR is not cleared after synchronizing.
+    DirtyNotCleared(usize),
+    /// All test cases on nested acceleration extension has passed.
+    Pass,
+}
+
+/// 所有已定义的特性，编号与下标相同。
+const FEATURES: [usize; 4] = [
+    feature_id::SYNC_CSR,
+    feature_id::SYNC_HFENCE,
+    feature_id::SYNC_SRET,
+    feature_id::AUTOSWAP_CSR,
+];
+
+/// 未定义的特性。
+const UNKNOWN_FEATURES: [u32; 2] = [0x4, 0xffff_ffff];
+
+/// 用于同步测试的 `vsscratch`，读写没有副作用。
+const CSR_VSSCRATCH: usize = 0x240;
+
+/// 共享内存中 CSR 脏位图的偏移。
+const DIRTY_BITMAP_OFFSET: usize = 0x0f80;
+/// 共享内存中 CSR 区域的偏移。
+const CSR_OFFSET: usize = 0x1000;
+
+#[repr(C, align(4096))]
+struct Shmem([u8; shmem_size::NATIVE]);
+
+static mut SHMEM: Shmem = Shmem([0; shmem_size::NATIVE]);
+
+/// Test nested acceleration extension.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_NACL).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    // 探测特性
+    let mut available = [false; FEATURES.len()];
+    for (i, feature_id) in FEATURES.into_iter().enumerate() {
+        let feature_id = feature_id as u32;
+        available[i] = sbi::nacl_probe_feature(feature_id).value != 0;
+        f(Case::Feature {
+            feature_id,
+            available: available[i],
+        });
+    }
+    for feature_id in UNKNOWN_FEATURES {
+        if sbi::nacl_probe_feature(feature_id).value != 0 {
+            f(Case::UnknownFeatureAvailable(feature_id));
+            return;
+        }
+    }
+    let sync_csr = available[feature_id::SYNC_CSR];
+    // 未设置共享内存时不能同步
+    let ret = disable();
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    if sync_csr {
+        let ret = sbi::nacl_sync_csr(usize::MAX);
+        if ret.err() != Some(Error::NoShmem) {
+            f(Case::NoShmemNotRejected(ret));
+            return;
+        }
+    }
+    // 检查设置共享内存的错误路径
+    let shmem = core::ptr::addr_of_mut!(SHMEM);
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem as usize + 8, 0), 0);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::MisalignedNotRejected(ret));
+        return;
+    }
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem as _, 0), 1);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::FlagsNotRejected(ret));
+        return;
+    }
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(0, usize::MAX >> 1), 0);
+    if ret.err() != Some(Error::InvalidAddress) {
+        f(Case::InvalidAddressNotRejected(ret));
+        return;
+    }
+    // 设置共享内存
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem as _, 0), 0);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let pass = test_unsupported(available, &mut f) && (!sync_csr || test_sync_csr(shmem, &mut f));
+    let ret = disable();
+    if pass && ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 调用不可用特性的函数应返回不支持。
+///
+/// 同步 `sret` 可用时会返回到虚拟机，因此只在不可用时调用。
+fn test_unsupported(available: [bool; FEATURES.len()], mut f: impl FnMut(Case)) -> bool {
+    for feature_id in [
+        feature_id::SYNC_CSR,
+        feature_id::SYNC_HFENCE,
+        feature_id::SYNC_SRET,
+    ] {
+        if available[feature_id] {
+            continue;
+        }
+        let ret = match feature_id {
+            feature_id::SYNC_CSR => sbi::nacl_sync_csr(usize::MAX),
+            feature_id::SYNC_HFENCE => sbi::nacl_sync_hfence(usize::MAX),
+            _ => sbi::nacl_sync_sret(),
+        };
+        if ret.err() != Some(Error::NotSupported) {
+            f(Case::UnsupportedNotRejected {
+                feature_id: feature_id as _,
+                ret,
+            });
+            return false;
+        }
+    }
+    true
+}
+
+/// 通过共享内存写入 `vsscratch` 并读回，最后恢复原值。
+fn test_sync_csr(shmem: *mut Shmem, mut f: impl FnMut(Case)) -> bool {
+    // 普通 S 态 CSR 不能同步
+    let ret = sbi::nacl_sync_csr(0x100);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::InvalidCsrNotRejected(ret));
+        return false;
+    }
+    let csr_num = CSR_VSSCRATCH;
+    // 读出原值
+    if !sync(csr_num, &mut f) {
+        return false;
+    }
+    let original = unsafe { csr(shmem, csr_num).read_volatile() };
+    let value = !original;
+    // 写入新值并标记为脏
+    unsafe {
+        csr(shmem, csr_num).write_volatile(value);
+        set_dirty(shmem, csr_num);
+    }
+    if !sync(csr_num, &mut f) {
+        return false;
+    }
+    if unsafe { is_dirty(shmem, csr_num) } {
+        f(Case::DirtyNotCleared(csr_num));
+        return false;
+    }
+    // 不标记为脏时，同步会以 CSR 的值覆盖共享内存
+    unsafe { csr(shmem, csr_num).write_volatile(original) };
+    if !sync(csr_num, &mut f) {
+        return false;
+    }
+    let actual = unsafe { csr(shmem, csr_num).read_volatile() };
+    if actual != value {
+        f(Case::CsrMismatch {
+            csr_num,
+            expected: value,
+            actual,
+        });
+        return false;
+    }
+    f(Case::SyncCsr { csr_num, value });
+    // 恢复原值
+    unsafe {
+        csr(shmem, csr_num).write_volatile(original);
+        set_dirty(shmem, csr_num);
+    }
+    sync(csr_num, &mut f)
+}
+
+/// 同步单个 CSR。
+fn sync(csr_num: usize, mut f: impl FnMut(Case)) -> bool {
+    let ret = sbi::nacl_sync_csr(csr_num);
+    if ret.is_err() {
+        f(Case::SyncCsrFailed { csr_num, ret });
+    }
+    ret.is_ok()
+}
+
+/// 共享内存中 CSR 的位置。
+///
+/// # Safety
+///
+/// `shmem` 必须指向有效的共享内存。
+#[inline]
+unsafe fn csr(shmem: *mut Shmem, csr_num: usize) -> *mut usize {
+    let base = (shmem as *mut u8).add(CSR_OFFSET) as *mut usize;
+    base.add(csr_index(csr_num))
+}
+
+/// 标记 CSR 为脏。
+///
+/// # Safety
+///
+/// `shmem` 必须指向有效的共享内存。
+#[inline]
+unsafe fn set_dirty(shmem: *mut Shmem, csr_num: usize) {
+    let index = csr_index(csr_num);
+    let byte = (shmem as *mut u8).add(DIRTY_BITMAP_OFFSET + index / 8);
+    byte.write_volatile(byte.read_volatile() | 1 << (index % 8));
+}
+
+/// 检查 CSR 是否为脏。
+///
+/// # Safety
+///
+/// `shmem` 必须指向有效的共享内存。
+#[inline]
+unsafe fn is_dirty(shmem: *mut Shmem, csr_num: usize) -> bool {
+    let index = csr_index(csr_num);
+    let byte = (shmem as *mut u8).add(DIRTY_BITMAP_OFFSET + index / 8);
+    byte.read_volatile() & 1 << (index % 8) != 0
+}
+
+/// CSR 在共享内存中的序号。
+#[inline]
+const fn csr_index(csr_num: usize) -> usize {
+    (csr_num & 0xc00) >> 2 | csr_num & 0xff
+}
+
+#[inline]
+fn disable() -> SbiRet {
+    sbi::nacl_set_shmem(SharedPtr::new(usize::MAX, usize::MAX), 0)
+}
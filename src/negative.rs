@@ -0,0 +1,138 @@
+//! Cross-extension test suite on calls to unsupported functions and extensions.
+
+use crate::thread::Thread;
+use riscv::register::{
+    scause::{self, Trap},
+    sepc,
+};
+use sbi::SbiRet;
+use sbi_spec::binary::RET_ERR_NOT_SUPPORTED;
+
+/// Unsupported function and extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Test begin.
+    Begin,
+    /// Test process for an unsupported call has been rejected with `SBI_ERR_NOT_SUPPORTED`.
+    Rejected {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+    },
+    /// Test failed for an unsupported call is not rejected with `SBI_ERR_NOT_SUPPORTED`.
+    NotRejected {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for an unsupported call trapped into supervisor mode.
+    Trapped {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// The trap caught upon the SBI call.
+        trap: Trap,
+    },
+    /// All test cases on unsupported calls has passed.
+    Pass,
+}
+
+/// 各标准扩展及其最大函数号之后的第一个函数号。
+const EXTENSIONS: [(usize, usize); 16] = [
+    (sbi_spec::base::EID_BASE, 7),
+    (sbi_spec::time::EID_TIME, 1),
+    (sbi_spec::spi::EID_SPI, 1),
+    (sbi_spec::rfnc::EID_RFNC, 7),
+    (sbi_spec::hsm::EID_HSM, 4),
+    (sbi_spec::srst::EID_SRST, 1),
+    (sbi_spec::pmu::EID_PMU, 9),
+    (sbi_spec::dbcn::EID_DBCN, 3),
+    (sbi_spec::susp::EID_SUSP, 1),
+    (sbi_spec::cppc::EID_CPPC, 4),
+    (sbi_spec::nacl::EID_NACL, 5),
+    (sbi_spec::sta::EID_STA, 1),
+    (crate::sse::EID_SSE, 10),
+    (crate::fwft::EID_FWFT, 2),
+    (crate::dbtr::EID_DBTR, 8),
+    (crate::mpxy::EID_MPXY, 8),
+];
+
+/// 较大的未定义函数号。
+const HIGH_FIDS: [usize; 3] = [0x100, 0x7fff_ffff, 0xffff_ffff];
+
+/// 未分配的扩展号。
+const UNALLOCATED_EIDS: [usize; 4] = [0x20, 0x4142_4344, 0x5a5a_5a5a, 0x7fff_ffff];
+
+/// Test unsupported calls.
+///
+/// For each available standard extension, the function ID next to the highest
+/// defined one and a few high function IDs are called. Then a few unallocated
+/// extension IDs are called. Each call is made in a thread, so that any trap
+/// is caught.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    f(Case::Begin);
+    let mut pass = true;
+    for (eid, next_fid) in EXTENSIONS {
+        if sbi::probe_extension(eid).is_unavailable() {
+            continue;
+        }
+        for fid in [next_fid].into_iter().chain(HIGH_FIDS) {
+            pass &= test_call(eid, fid, &mut f);
+        }
+    }
+    for eid in UNALLOCATED_EIDS {
+        pass &= test_call(eid, 0, &mut f);
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 在线程中发起调用，检查调用返回不支持且未陷入。
+fn test_call(eid: usize, fid: usize, mut f: impl FnMut(Case)) -> bool {
+    use scause::Exception;
+
+    let entry = call as usize;
+    let mut thread = Thread::new(entry);
+    *thread.a_mut(6) = fid;
+    *thread.a_mut(7) = eid;
+    unsafe { thread.execute() };
+    // 调用返回后应在其后的 `unimp` 处陷入
+    let trap = scause::read().cause();
+    if trap != Trap::Exception(Exception::IllegalInstruction) || sepc::read() != entry + 4 {
+        f(Case::Trapped { eid, fid, trap });
+        return false;
+    }
+    let ret = SbiRet {
+        error: thread.a(0),
+        value: thread.a(1),
+    };
+    if ret.error != RET_ERR_NOT_SUPPORTED {
+        f(Case::NotRejected { eid, fid, ret });
+        return false;
+    }
+    f(Case::Rejected { eid, fid });
+    true
+}
+
+/// 以线程上下文中的参数发起调用，返回后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn call() {
+    core::arch::asm!(
+        "   ecall
+            unimp
+        ",
+        options(noreturn)
+    )
+}
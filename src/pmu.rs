@@ -0,0 +1,807 @@
+//! Performance monitoring unit extension test suite.
+
+use crate::thread::Thread;
+use riscv::register::scause::Trap;
+use sbi::SbiRet;
+
+/// Performance monitoring unit extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for performance monitoring unit extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for getting number of counters.
+    NumCounters(usize),
+    /// Test process for getting details of a counter.
+    CounterInfo {
+        /// Logical index of the counter.
+        idx: usize,
+        /// Decoded details of the counter.
+        info: CounterInfo,
+    },
+    /// Test failed for can't get details of a counter with [`SbiRet`] error.
+    GetInfoFailed {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for details of a counter is implausible.
+    InvalidCounterInfo {
+        /// Logical index of the counter.
+        idx: usize,
+        /// Raw `counter_info` value returned by SBI implementation.
+        raw: usize,
+    },
+    /// Test process for an event is not supported by SBI implementation.
+    EventNotSupported(usize),
+    /// Test failed for can't configure a counter for an event with [`SbiRet`] error.
+    ConfigMatchingFailed {
+        /// The event index to be counted.
+        event_idx: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for can't start a counter with [`SbiRet`] error.
+    CounterStartFailed {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for can't stop a counter with [`SbiRet`] error.
+    CounterStopFailed {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for starting a started counter does not return `SBI_ERR_ALREADY_STARTED`.
+    AlreadyStartedNotReported {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for stopping a stopped counter does not return `SBI_ERR_ALREADY_STOPPED`.
+    AlreadyStoppedNotReported {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for can't read CSR of a hardware counter.
+    CounterReadFailed {
+        /// Logical index of the counter.
+        idx: usize,
+        /// CSR number of the counter.
+        csr: u16,
+    },
+    /// Test failed for hardware counter value does not grow in line with instructions executed.
+    CounterNotGrowing {
+        /// The event index counted.
+        event_idx: usize,
+        /// Counter values after a short loop and a loop four times as long.
+        values: [usize; 2],
+    },
+    /// Test process for a hardware event has been counted.
+    HardwareEvent {
+        /// The event index counted.
+        event_idx: usize,
+        /// Logical index of the counter.
+        idx: usize,
+        /// Counter values after a short loop and a loop four times as long.
+        values: [usize; 2],
+    },
+    /// Test failed for can't read a firmware counter with [`SbiRet`] error.
+    FirmwareReadFailed {
+        /// Logical index of the counter.
+        idx: usize,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for firmware counter value does not match number of SBI calls made.
+    FirmwareCountMismatch {
+        /// The event index counted.
+        event_idx: usize,
+        /// Number of events caused by this test.
+        expected: usize,
+        /// Value read from the firmware counter.
+        actual: usize,
+    },
+    /// Test failed for unexpected trap occurred upon waiting for an interrupt.
+    UnexpectedTrap(Trap),
+    /// Test process for a firmware event has been counted.
+    FirmwareEvent {
+        /// The event index counted.
+        event_idx: usize,
+        /// Logical index of the counter.
+        idx: usize,
+        /// Value read from the firmware counter.
+        count: usize,
+    },
+    /// Test process for counter overflow interrupt is not supported by the hart.
+    OverflowNotSupported,
+    /// Test process for counter overflow interrupt is not tested for no `hpmcounter3` to `hpmcounter31` is available.
+    NoOverflowCounter,
+    /// Test process for counter overflow interrupt has been received.
+    CounterOverflow {
+        /// Logical index of the counter.
+        idx: usize,
+    },
+    /// Test process for snapshot shared memory is not supported by SBI implementation.
+    SnapshotNotSupported,
+    /// Test failed for can't set snapshot shared memory with [`SbiRet`] error.
+    SnapshotSetShmemFailed(SbiRet),
+    /// Test failed for misaligned snapshot shared memory is not rejected with `SBI_ERR_INVALID_PARAM`.
+    SnapshotMisalignedNotRejected(SbiRet),
+    /// Test failed for invalid snapshot shared memory is not rejected with `SBI_ERR_INVALID_ADDRESS`.
+    SnapshotInvalidAddressNotRejected(SbiRet),
+    /// Test failed for the logical counter index is beyond counter values in snapshot shared memory.
+    SnapshotIndexOutOfRange(usize),
+    /// Test failed for counter value in snapshot shared memory does not match direct read.
+    SnapshotMismatch {
+        /// Logical index of the counter.
+        idx: usize,
+        /// Counter value in snapshot shared memory.
+        snapshot: u64,
+        /// Counter value read from counter CSR.
+        direct: usize,
+    },
+    /// Test process for counter value in snapshot shared memory matches direct read.
+    Snapshot {
+        /// Logical index of the counter.
+        idx: usize,
+        /// Counter value in snapshot shared memory.
+        value: u64,
+    },
+    /// All test cases on performance monitoring unit extension has passed.
+    Pass,
+}
+
+/// Details of a performance monitoring unit counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterInfo {
+    /// Hardware counter.
+    Hardware {
+        /// CSR number of the counter.
+        csr: u16,
+        /// Number of bits in the counter.
+        width: u8,
+    },
+    /// Firmware counter.
+    Firmware,
+}
+
+const CSR_CYCLE: u16 = 0xc00;
+const CSR_TIME: u16 = 0xc01;
+const CSR_INSTRET: u16 = 0xc02;
+const CSR_HPMCOUNTER3: u16 = 0xc03;
+const CSR_HPMCOUNTER31: u16 = 0xc1f;
+
+impl CounterInfo {
+    /// 解码 `counter_info`，保留位不为零时返回 `None`。
+    fn from_raw(raw: usize) -> Option<Self> {
+        const TYPE_FIRMWARE: usize = 1 << (usize::BITS - 1);
+        const RESERVED: usize = !TYPE_FIRMWARE & !((1 << 18) - 1);
+        if raw & RESERVED != 0 {
+            None
+        } else if raw & TYPE_FIRMWARE != 0 {
+            Some(Self::Firmware)
+        } else {
+            Some(Self::Hardware {
+                csr: (raw & 0xfff) as _,
+                width: ((raw >> 12) & 0x3f) as u8 + 1,
+            })
+        }
+    }
+
+    /// 硬件计数器必须是 `cycle`、`instret` 或 `hpmcounter3`~`hpmcounter31` 之一，
+    /// 且 `cycle` 和 `instret` 必须是 64 位的。
+    fn is_plausible(&self) -> bool {
+        match *self {
+            Self::Hardware { csr, width } => match csr {
+                CSR_CYCLE | CSR_INSTRET => width == 64,
+                CSR_TIME => false,
+                CSR_CYCLE..=CSR_HPMCOUNTER31 => true,
+                _ => false,
+            },
+            Self::Firmware => true,
+        }
+    }
+}
+
+/// Test performance monitoring unit extension.
+///
+/// Firmware counters are cross-checked by setting timers with `delay`
+/// and sending inter-processor interrupts to current hart `hart_id`.
+/// Counter overflow interrupt is expected to arrive before a timer of `delay`.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hart_id: usize, delay: u64, mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Pmu).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    if !test_counter_info(&mut f) {
+        return;
+    }
+    if !test_hardware_events(&mut f) {
+        return;
+    }
+    if !test_firmware_events(hart_id, delay, &mut f) {
+        return;
+    }
+    if !test_overflow(delay, &mut f) {
+        return;
+    }
+    if !test_snapshot(&mut f) {
+        return;
+    }
+    f(Case::Pass);
+}
+
+/// 获取并检查所有计数器的信息。
+fn test_counter_info(mut f: impl FnMut(Case)) -> bool {
+    let num_counters = sbi::pmu_num_counters();
+    f(Case::NumCounters(num_counters));
+    let mut pass = true;
+    // 记录已出现的硬件计数器，同一个 CSR 不能对应多个计数器
+    let mut csr_used = 0u32;
+    for idx in 0..num_counters {
+        let ret = sbi::pmu_counter_get_info(idx);
+        let Some(raw) = ret.ok() else {
+            f(Case::GetInfoFailed { idx, ret });
+            pass = false;
+            continue;
+        };
+        match CounterInfo::from_raw(raw) {
+            Some(info) if info.is_plausible() => {
+                if let CounterInfo::Hardware { csr, .. } = info {
+                    let bit = 1 << (csr - CSR_CYCLE);
+                    if csr_used & bit != 0 {
+                        f(Case::InvalidCounterInfo { idx, raw });
+                        pass = false;
+                        continue;
+                    }
+                    csr_used |= bit;
+                }
+                f(Case::CounterInfo { idx, info });
+            }
+            _ => {
+                f(Case::InvalidCounterInfo { idx, raw });
+                pass = false;
+            }
+        }
+    }
+    pass
+}
+
+const CFG_FLAG_CLEAR_VALUE: usize = 1 << 1;
+const START_FLAG_SET_INIT_VALUE: usize = 1 << 0;
+const START_FLAG_INIT_SNAPSHOT: usize = 1 << 1;
+const STOP_FLAG_RESET: usize = 1 << 0;
+const STOP_FLAG_TAKE_SNAPSHOT: usize = 1 << 1;
+
+/// 测试用循环的迭代次数。
+const LOOP_COUNT: usize = 10000;
+
+/// 覆盖所有计数器的掩码。
+fn all_counters() -> usize {
+    match sbi::pmu_num_counters() {
+        n if n >= usize::BITS as usize => usize::MAX,
+        n => (1 << n) - 1,
+    }
+}
+
+/// 使用硬件计数器对周期和指令计数。
+fn test_hardware_events(mut f: impl FnMut(Case)) -> bool {
+    use sbi_spec::pmu::hardware_event::{CPU_CYCLES, INSTRUCTIONS};
+
+    let mut pass = true;
+    for event_idx in [CPU_CYCLES, INSTRUCTIONS] {
+        pass &= test_hardware_event(event_idx, &mut f);
+    }
+    pass
+}
+
+/// 使用一个硬件计数器对 `event_idx` 计数，检查计数器启停的错误路径和计数值。
+fn test_hardware_event(event_idx: usize, mut f: impl FnMut(Case)) -> bool {
+    use sbi_spec::binary::Error;
+
+    let ret =
+        sbi::pmu_counter_config_matching(0, all_counters(), CFG_FLAG_CLEAR_VALUE, event_idx, 0);
+    let idx = match ret.err() {
+        None => ret.value,
+        Some(Error::NotSupported) => {
+            f(Case::EventNotSupported(event_idx));
+            return true;
+        }
+        Some(_) => {
+            f(Case::ConfigMatchingFailed { event_idx, ret });
+            return false;
+        }
+    };
+    // 硬件事件必须匹配到硬件计数器
+    let info = sbi::pmu_counter_get_info(idx);
+    let csr = match info.ok().and_then(CounterInfo::from_raw) {
+        Some(CounterInfo::Hardware { csr, .. }) => csr,
+        _ => {
+            f(Case::InvalidCounterInfo {
+                idx,
+                raw: info.value,
+            });
+            let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+            return false;
+        }
+    };
+    // 第一轮：检查重复启动和重复停止
+    let ret = sbi::pmu_counter_start(idx, 1, 0, 0);
+    if ret.is_err() {
+        f(Case::CounterStartFailed { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    let ret = sbi::pmu_counter_start(idx, 1, 0, 0);
+    if ret.err() != Some(Error::AlreadyStarted) {
+        f(Case::AlreadyStartedNotReported { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    busy_loop(LOOP_COUNT);
+    let ret = sbi::pmu_counter_stop(idx, 1, 0);
+    if ret.is_err() {
+        f(Case::CounterStopFailed { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    let ret = sbi::pmu_counter_stop(idx, 1, 0);
+    if ret.err() != Some(Error::AlreadyStopped) {
+        f(Case::AlreadyStoppedNotReported { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    let Some(short) = read_counter(csr) else {
+        f(Case::CounterReadFailed { idx, csr });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    };
+    // 第二轮：从零开始计数更长的循环，读取后释放计数器
+    let ret = sbi::pmu_counter_start(idx, 1, START_FLAG_SET_INIT_VALUE, 0);
+    if ret.is_err() {
+        f(Case::CounterStartFailed { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    busy_loop(LOOP_COUNT * 4);
+    let ret = sbi::pmu_counter_stop(idx, 1, 0);
+    if ret.is_err() {
+        f(Case::CounterStopFailed { idx, ret });
+        let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+        return false;
+    }
+    let long = read_counter(csr);
+    let _ = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_RESET);
+    let Some(long) = long else {
+        f(Case::CounterReadFailed { idx, csr });
+        return false;
+    };
+    // 循环每次迭代执行两条指令，迭代间的数据依赖使每次迭代至少一个周期；
+    // 长循环是短循环的四倍，扣除调用开销后计数值应至少翻倍
+    let values = [short, long];
+    let min = match event_idx {
+        sbi_spec::pmu::hardware_event::INSTRUCTIONS => LOOP_COUNT * 2,
+        _ => LOOP_COUNT,
+    };
+    if short < min || long < short.saturating_mul(2) {
+        f(Case::CounterNotGrowing { event_idx, values });
+        return false;
+    }
+    f(Case::HardwareEvent {
+        event_idx,
+        idx,
+        values,
+    });
+    true
+}
+
+/// 测试用定时器中断和核间中断的次数。
+const FIRMWARE_EVENT_COUNT: usize = 8;
+
+/// 使用固件计数器对定时器设置和核间中断发送计数，与本测试发起的调用次数比对。
+fn test_firmware_events(hart_id: usize, delay: u64, mut f: impl FnMut(Case)) -> bool {
+    use crate::{spi::trap_ipi, time::trap_timer};
+    use riscv::register::scause::Interrupt;
+    use sbi_spec::{
+        binary::Error,
+        pmu::{
+            event_type::FIRMWARE,
+            firmware_event::{IPI_SENT, SET_TIMER},
+        },
+    };
+
+    let events = [SET_TIMER, IPI_SENT].map(|code| (FIRMWARE << 16) | code);
+    let mut counters = [None; 2];
+    for (event_idx, counter) in events.into_iter().zip(&mut counters) {
+        let ret =
+            sbi::pmu_counter_config_matching(0, all_counters(), CFG_FLAG_CLEAR_VALUE, event_idx, 0);
+        match ret.err() {
+            None => *counter = Some(ret.value),
+            Some(Error::NotSupported) => f(Case::EventNotSupported(event_idx)),
+            Some(_) => {
+                f(Case::ConfigMatchingFailed { event_idx, ret });
+                release(counters.iter().flatten());
+                return false;
+            }
+        }
+    }
+    for idx in counters.iter().flatten().copied() {
+        let ret = sbi::pmu_counter_start(idx, 1, 0, 0);
+        if ret.is_err() {
+            f(Case::CounterStartFailed { idx, ret });
+            release(counters.iter().flatten());
+            return false;
+        }
+    }
+    // 每次定时器测试设置两次定时器，每次核间中断测试发送一次核间中断
+    for _ in 0..FIRMWARE_EVENT_COUNT {
+        let trap = trap_timer(delay);
+        sbi::set_timer(u64::MAX);
+        if trap != Trap::Interrupt(Interrupt::SupervisorTimer) {
+            f(Case::UnexpectedTrap(trap));
+            release(counters.iter().flatten());
+            return false;
+        }
+        let trap = trap_ipi(hart_id);
+        if trap != Trap::Interrupt(Interrupt::SupervisorSoft) {
+            f(Case::UnexpectedTrap(trap));
+            release(counters.iter().flatten());
+            return false;
+        }
+    }
+    for idx in counters.iter().flatten().copied() {
+        let ret = sbi::pmu_counter_stop(idx, 1, 0);
+        if ret.is_err() {
+            f(Case::CounterStopFailed { idx, ret });
+            release(counters.iter().flatten());
+            return false;
+        }
+    }
+    let expected = [FIRMWARE_EVENT_COUNT * 2, FIRMWARE_EVENT_COUNT];
+    let mut pass = true;
+    for ((event_idx, counter), expected) in events.into_iter().zip(counters).zip(expected) {
+        let Some(idx) = counter else {
+            continue;
+        };
+        let ret = sbi::pmu_counter_fw_read(idx);
+        match ret.ok() {
+            Some(count) if count == expected => f(Case::FirmwareEvent {
+                event_idx,
+                idx,
+                count,
+            }),
+            Some(actual) => {
+                f(Case::FirmwareCountMismatch {
+                    event_idx,
+                    expected,
+                    actual,
+                });
+                pass = false;
+            }
+            None => {
+                f(Case::FirmwareReadFailed { idx, ret });
+                pass = false;
+            }
+        }
+    }
+    release(counters.iter().flatten());
+    pass
+}
+
+/// 停止并释放计数器。
+fn release<'a>(counters: impl IntoIterator<Item = &'a usize>) {
+    for idx in counters {
+        let _ = sbi::pmu_counter_stop(*idx, 1, STOP_FLAG_RESET);
+    }
+}
+
+/// `hpmcounter3` 到 `hpmcounter31` 对应的计数器掩码。
+fn overflow_counters() -> usize {
+    let num_counters = sbi::pmu_num_counters().min(usize::BITS as usize);
+    (0..num_counters)
+        .filter(|&idx| {
+            let info = sbi::pmu_counter_get_info(idx);
+            matches!(
+                info.ok().and_then(CounterInfo::from_raw),
+                Some(CounterInfo::Hardware { csr, .. }) if csr >= CSR_HPMCOUNTER3
+            )
+        })
+        .fold(0, |mask, idx| mask | 1 << idx)
+}
+
+/// 计数器初始值距离溢出的事件数。
+const OVERFLOW_DISTANCE: u64 = 1000;
+
+/// 设置一个即将溢出的周期计数器，在线程中等待计数器溢出中断。
+///
+/// 以 `delay` 之后的定时器中断作为超时，超时视为意外的陷入。
+fn test_overflow(delay: u64, mut f: impl FnMut(Case)) -> bool {
+    use riscv::register::{scause, sie, time};
+    use sbi_spec::{binary::Error, pmu::hardware_event::CPU_CYCLES};
+
+    const LCOFI: usize = 13;
+    // 检查是否支持 Sscofpmf 扩展
+    unsafe { core::arch::asm!("csrs sie, {}", in(reg) 1 << LCOFI) };
+    if sie::read().bits() & (1 << LCOFI) == 0 {
+        f(Case::OverflowNotSupported);
+        return true;
+    }
+    // 固定的 cycle 和 instret 计数器不能产生溢出中断，只使用可编程硬件计数器
+    let counters = overflow_counters();
+    if counters == 0 {
+        unsafe { core::arch::asm!("csrc sie, {}", in(reg) 1 << LCOFI) };
+        f(Case::NoOverflowCounter);
+        return true;
+    }
+    let event_idx = CPU_CYCLES;
+    let ret = sbi::pmu_counter_config_matching(0, counters, CFG_FLAG_CLEAR_VALUE, event_idx, 0);
+    let idx = match ret.err() {
+        None => ret.value,
+        Some(Error::NotSupported) => {
+            f(Case::EventNotSupported(event_idx));
+            return true;
+        }
+        Some(_) => {
+            f(Case::ConfigMatchingFailed { event_idx, ret });
+            return false;
+        }
+    };
+    let info = sbi::pmu_counter_get_info(idx);
+    let width = match info.ok().and_then(CounterInfo::from_raw) {
+        Some(CounterInfo::Hardware { width, .. }) => width,
+        _ => {
+            f(Case::InvalidCounterInfo {
+                idx,
+                raw: info.value,
+            });
+            release([&idx]);
+            return false;
+        }
+    };
+    let initial_value = match width {
+        64 => u64::MAX - OVERFLOW_DISTANCE,
+        width => (1 << width) - OVERFLOW_DISTANCE,
+    };
+
+    fn spin() -> ! {
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    let mut stack = [0usize; 32];
+    let mut thread = Thread::new(spin as _);
+    *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
+
+    sbi::set_timer(time::read64() + delay);
+    let ret = sbi::pmu_counter_start(idx, 1, START_FLAG_SET_INIT_VALUE, initial_value);
+    if ret.is_err() {
+        sbi::set_timer(u64::MAX);
+        f(Case::CounterStartFailed { idx, ret });
+        release([&idx]);
+        return false;
+    }
+    unsafe {
+        sie::set_stimer();
+        thread.execute();
+    }
+    let scause = scause::read();
+    sbi::set_timer(u64::MAX);
+    release([&idx]);
+    unsafe {
+        core::arch::asm!(
+            "csrc sip, {0}",
+            "csrc sie, {0}",
+            in(reg) 1 << LCOFI,
+        )
+    };
+    if scause.is_interrupt() && scause.code() == LCOFI {
+        f(Case::CounterOverflow { idx });
+        true
+    } else {
+        f(Case::UnexpectedTrap(scause.cause()));
+        false
+    }
+}
+
+/// 快照共享内存。
+#[repr(C, align(4096))]
+struct Snapshot {
+    counter_overflow_bitmap: u64,
+    counter_values: [u64; 64],
+    reserved: [u64; 447],
+}
+
+static mut SNAPSHOT: Snapshot = Snapshot {
+    counter_overflow_bitmap: 0,
+    counter_values: [0; 64],
+    reserved: [0; 447],
+};
+
+/// 快照起始计数值。
+const SNAPSHOT_INIT_VALUE: u64 = 0x1000;
+
+/// 设置快照共享内存，通过快照启停指令计数器，将快照中的计数值与直接读取的值比对。
+fn test_snapshot(mut f: impl FnMut(Case)) -> bool {
+    use crate::binary::sbi_call_3;
+    use sbi_spec::{
+        binary::Error,
+        pmu::{hardware_event::INSTRUCTIONS, EID_PMU, SNAPSHOT_SET_SHMEM},
+    };
+
+    let set_shmem = |lo, hi| sbi_call_3(EID_PMU, SNAPSHOT_SET_SHMEM, lo, hi, 0);
+    let shmem = core::ptr::addr_of_mut!(SNAPSHOT);
+    // 检查错误路径
+    let ret = set_shmem(shmem as usize + 8, 0);
+    match ret.err() {
+        Some(Error::NotSupported) => {
+            f(Case::SnapshotNotSupported);
+            return true;
+        }
+        Some(Error::InvalidParam) => {}
+        _ => {
+            f(Case::SnapshotMisalignedNotRejected(ret));
+            return false;
+        }
+    }
+    let ret = set_shmem(0, usize::MAX >> 1);
+    if ret.err() != Some(Error::InvalidAddress) {
+        f(Case::SnapshotInvalidAddressNotRejected(ret));
+        return false;
+    }
+    // 设置快照共享内存
+    let ret = set_shmem(shmem as _, 0);
+    if ret.is_err() {
+        f(Case::SnapshotSetShmemFailed(ret));
+        return false;
+    }
+    let pass = test_snapshot_counter(INSTRUCTIONS, shmem, &mut f);
+    // 关闭快照共享内存
+    let ret = set_shmem(usize::MAX, usize::MAX);
+    if ret.is_err() {
+        f(Case::SnapshotSetShmemFailed(ret));
+        return false;
+    }
+    pass
+}
+
+/// 以快照中的值启动 `event_idx` 的计数器，停止时保存快照并与直接读取的值比对。
+fn test_snapshot_counter(event_idx: usize, shmem: *mut Snapshot, mut f: impl FnMut(Case)) -> bool {
+    use sbi_spec::binary::Error;
+
+    let ret =
+        sbi::pmu_counter_config_matching(0, all_counters(), CFG_FLAG_CLEAR_VALUE, event_idx, 0);
+    let idx = match ret.err() {
+        None => ret.value,
+        Some(Error::NotSupported) => {
+            f(Case::EventNotSupported(event_idx));
+            return true;
+        }
+        Some(_) => {
+            f(Case::ConfigMatchingFailed { event_idx, ret });
+            return false;
+        }
+    };
+    let info = sbi::pmu_counter_get_info(idx);
+    let csr = match info.ok().and_then(CounterInfo::from_raw) {
+        Some(CounterInfo::Hardware { csr, .. }) => csr,
+        _ => {
+            f(Case::InvalidCounterInfo {
+                idx,
+                raw: info.value,
+            });
+            release([&idx]);
+            return false;
+        }
+    };
+    // 逻辑编号来自固件，超出快照范围时不能用作下标
+    if idx >= unsafe { (*shmem).counter_values.len() } {
+        f(Case::SnapshotIndexOutOfRange(idx));
+        release([&idx]);
+        return false;
+    }
+    unsafe {
+        let value = core::ptr::addr_of_mut!((*shmem).counter_values[idx]);
+        value.write_volatile(SNAPSHOT_INIT_VALUE);
+    }
+    let ret = sbi::pmu_counter_start(idx, 1, START_FLAG_INIT_SNAPSHOT, 0);
+    if ret.is_err() {
+        f(Case::CounterStartFailed { idx, ret });
+        release([&idx]);
+        return false;
+    }
+    busy_loop(LOOP_COUNT);
+    let ret = sbi::pmu_counter_stop(idx, 1, STOP_FLAG_TAKE_SNAPSHOT);
+    if ret.is_err() {
+        f(Case::CounterStopFailed { idx, ret });
+        release([&idx]);
+        return false;
+    }
+    let snapshot = unsafe { core::ptr::addr_of!((*shmem).counter_values[idx]).read_volatile() };
+    let direct = read_counter(csr);
+    release([&idx]);
+    let Some(direct) = direct else {
+        f(Case::CounterReadFailed { idx, csr });
+        return false;
+    };
+    if snapshot != direct as u64 || snapshot < SNAPSHOT_INIT_VALUE {
+        f(Case::SnapshotMismatch {
+            idx,
+            snapshot,
+            direct,
+        });
+        return false;
+    }
+    f(Case::Snapshot {
+        idx,
+        value: snapshot,
+    });
+    true
+}
+
+/// 执行 `n` 次迭代的循环，每次迭代两条指令。
+#[inline(never)]
+fn busy_loop(n: usize) {
+    unsafe {
+        core::arch::asm!(
+            "1: addi {n}, {n}, -1
+                bnez {n}, 1b
+            ",
+            n = inlateout(reg) n => _,
+        );
+    }
+}
+
+/// 在线程中读取硬件计数器 CSR，读取时发生异常则返回 `None`。
+fn read_counter(csr: u16) -> Option<usize> {
+    use riscv::register::{scause::Exception, sepc};
+
+    if !(CSR_CYCLE..=CSR_HPMCOUNTER31).contains(&csr) {
+        return None;
+    }
+    // 每个 CSR 的读取入口占两条非压缩指令
+    let entry = read_counters as usize + (csr - CSR_CYCLE) as usize * 8;
+    let mut thread = Thread::new(entry);
+    unsafe { thread.execute() };
+    let trap = riscv::register::scause::read().cause();
+    (trap == Trap::Exception(Exception::IllegalInstruction) && sepc::read() == entry + 4)
+        .then(|| thread.a(0))
+}
+
+/// 读取所有硬件计数器 CSR 的入口表，每个入口读取一个 CSR，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn read_counters() {
+    core::arch::asm!(
+        r"  .option push
+            .option norvc
+            .altmacro
+            .macro READ_COUNTER n
+                csrr a0, \n
+                unimp
+            .endm
+            .set n, 0xc00
+            .rept 32
+                READ_COUNTER %n
+                .set n, n+1
+            .endr
+            .option pop
+        ",
+        options(noreturn)
+    )
+}
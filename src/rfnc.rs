@@ -0,0 +1,200 @@
+//! Remote fence extension test suite.
+
+use crate::batch::{self, Batches, Event, ItemPerHart};
+use core::sync::atomic::Ordering;
+use sbi::SbiRet;
+use sbi_spec::{binary::HartMask, hsm::hart_state};
+
+/// Remote fence extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case<'a> {
+    /// Can't procceed test for remote fence extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for remote `FENCE.I` instruction executed on target harts.
+    RemoteFenceI(HartMask),
+    /// Test failed for remote `FENCE.I` instruction returned an error.
+    RemoteFenceIFailed {
+        /// The harts targeted by this SBI call.
+        hart_mask: HartMask,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for remote `SFENCE.VMA` instruction executed on target harts.
+    RemoteSfenceVma(HartMask),
+    /// Test failed for remote `SFENCE.VMA` instruction returned an error.
+    RemoteSfenceVmaFailed {
+        /// The harts targeted by this SBI call.
+        hart_mask: HartMask,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for remote `SFENCE.VMA` instruction with ASID executed on target harts.
+    RemoteSfenceVmaAsid(HartMask),
+    /// Test failed for remote `SFENCE.VMA` instruction with ASID returned an error.
+    RemoteSfenceVmaAsidFailed {
+        /// The harts targeted by this SBI call.
+        hart_mask: HartMask,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for hart started before test begin.
+    ///
+    /// The returned value includes which hart led to this test failure.
+    HartStartedBeforeTest(usize),
+    /// Remote harts are not tested for no other harts are available to be started.
+    NoStoppedHart,
+    /// Test process for begin test remote fences on one batch of secondary harts.
+    BatchBegin(&'a [usize]),
+    /// Test failed for can't start target hart with [`SbiRet`] error.
+    HartStartFailed {
+        /// The target hart ID that has failed to start.
+        hartid: usize,
+        /// The `SbiRet` value for the failed hart start SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for harts on current batch has passed the tests.
+    BatchPass(&'a [usize]),
+    /// All test cases on remote fence extension has passed.
+    Pass,
+}
+
+/// Test remote fence extension on local hart and given secondary harts.
+///
+/// Secondary harts are brought up through hart state monitor extension,
+/// so they are only tested if that extension exists.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(
+    primary_hart_id: usize,
+    hart_mask: usize,
+    hart_mask_base: usize,
+    mut f: impl FnMut(Case),
+) {
+    if sbi::probe_extension(sbi::Fence).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    // 测试本核
+    if !test_fences(HartMask::from_mask_base(1, primary_hart_id), &mut f) {
+        return;
+    }
+    // 不支持 HSM 扩展，无法启动副核
+    if sbi::probe_extension(sbi::Hsm).is_unavailable() {
+        f(Case::NoStoppedHart);
+        f(Case::Pass);
+        return;
+    }
+    // 分批测试
+    let result =
+        batch::for_each_batch(
+            primary_hart_id,
+            hart_mask,
+            hart_mask_base,
+            |event| match event {
+                Event::StartedBeforeTest(hartid) => {
+                    f(Case::HartStartedBeforeTest(hartid));
+                    true
+                }
+                Event::Batch(batch) => test_batch(batch, &mut f),
+            },
+        );
+    match result {
+        Batches::Passed => f(Case::Pass),
+        Batches::Failed => {}
+        // 没有找到能参与测试的副核，本核已通过测试
+        Batches::NoStoppedHart => {
+            f(Case::NoStoppedHart);
+            f(Case::Pass);
+        }
+    }
+}
+
+const STARTED: SbiRet = SbiRet::success(hart_state::STARTED);
+const STOPPED: SbiRet = SbiRet::success(hart_state::STOPPED);
+
+/// 用于测试带地址范围的刷新的虚拟地址。
+const TEST_ADDR: usize = 0x8000_0000;
+/// 用于测试带地址范围的刷新的范围大小。
+const TEST_SIZE: usize = 4096;
+/// 用于测试带 ASID 的刷新的地址空间标识符。
+const TEST_ASID: usize = 0;
+
+/// 向目标核发出所有远程屏障。
+fn test_fences(hart_mask: HartMask, mut f: impl FnMut(Case)) -> bool {
+    let ret = sbi::remote_fence_i(hart_mask);
+    if ret.is_err() {
+        f(Case::RemoteFenceIFailed { hart_mask, ret });
+        return false;
+    }
+    f(Case::RemoteFenceI(hart_mask));
+    // 分别测试全部刷新和范围刷新
+    for (start_addr, size) in [(0, usize::MAX), (TEST_ADDR, TEST_SIZE)] {
+        let ret = sbi::remote_sfence_vma(hart_mask, start_addr, size);
+        if ret.is_err() {
+            f(Case::RemoteSfenceVmaFailed { hart_mask, ret });
+            return false;
+        }
+    }
+    f(Case::RemoteSfenceVma(hart_mask));
+    for (start_addr, size) in [(0, usize::MAX), (TEST_ADDR, TEST_SIZE)] {
+        let ret = sbi::remote_sfence_vma_asid(hart_mask, start_addr, size, TEST_ASID);
+        if ret.is_err() {
+            f(Case::RemoteSfenceVmaAsidFailed { hart_mask, ret });
+            return false;
+        }
+    }
+    f(Case::RemoteSfenceVmaAsid(hart_mask));
+    true
+}
+
+const STAGE_STARTED: u32 = 1;
+
+/// 测试一批核
+fn test_batch(batch: &[usize], mut f: impl FnMut(Case)) -> bool {
+    f(Case::BatchBegin(batch));
+    // 启动这些核
+    for (i, hartid) in batch.iter().copied().enumerate() {
+        let ret = batch::start(hartid, i, rust_main);
+        if ret.is_err() {
+            f(Case::HartStartFailed { hartid, ret });
+            return false;
+        }
+    }
+    // 等待这些核进入 rust 并保持运行
+    for (i, hartid) in batch.iter().copied().enumerate() {
+        while sbi::hart_get_status(hartid) != STARTED {
+            core::hint::spin_loop();
+        }
+        batch::item(i).wait_stage(STAGE_STARTED);
+    }
+    // 逐个测试，再整批测试
+    let passed = batch
+        .iter()
+        .map(|hartid| HartMask::from_mask_base(1, *hartid))
+        .chain([batch::hart_mask(batch)])
+        .all(|hart_mask| test_fences(hart_mask, &mut f));
+    // 无论测试结果如何，都要关闭这些核
+    for (i, hartid) in batch.iter().copied().enumerate() {
+        batch::item(i).send_signal();
+        while sbi::hart_get_status(hartid) != STOPPED {
+            core::hint::spin_loop();
+        }
+    }
+    if passed {
+        f(Case::BatchPass(batch));
+    }
+    passed
+}
+
+#[inline(never)]
+extern "C" fn rust_main(hart_id: usize, opaque: *mut ItemPerHart) -> ! {
+    let item = unsafe { &mut *opaque };
+    item.stage.store(STAGE_STARTED, Ordering::Release);
+    // 保持运行，直到主核完成对本核的远程屏障测试
+    item.wait_signal();
+    let ret = sbi::hart_stop();
+    unreachable!("stop [{hart_id}] but {ret:?}")
+}
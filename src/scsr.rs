@@ -0,0 +1,271 @@
+//! Cross-extension test suite on preservation of supervisor CSRs.
+
+use crate::{
+    binary::{harmless_calls, Call},
+    thread::Thread,
+};
+use riscv::register::{
+    satp,
+    scause::{self, Exception, Trap},
+    sepc, sstatus,
+};
+
+/// Supervisor CSR preservation test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Test begin.
+    Begin,
+    /// Test process for a CSR is not checked for it is not implemented, does not hold the test value, or is in use.
+    Skipped(&'static str),
+    /// Test process for all checked CSRs are preserved across an SBI call.
+    Preserved {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+    },
+    /// Test failed for a CSR is modified by an SBI call.
+    Clobbered {
+        /// The extension ID.
+        eid: usize,
+        /// The function ID.
+        fid: usize,
+        /// Name of the modified CSR.
+        csr: &'static str,
+        /// The value before the SBI call.
+        expected: usize,
+        /// The value after the SBI call.
+        actual: usize,
+    },
+    /// Test is stopped for more SBI calls are made than the test could record.
+    TooManyCalls,
+    /// All test cases on supervisor CSR preservation has passed.
+    Pass,
+}
+
+/// 检查的 CSR，顺序与读写函数相同。
+const CSRS: [&str; 6] = ["sscratch", "stvec", "sepc", "stval", "satp", "senvcfg"];
+const SATP: usize = 4;
+const SENVCFG: usize = 5;
+
+/// 最多记录的调用数量。
+const MAX_CALLS: usize = 32;
+
+/// Sv39 模式，以 1 GiB 大页恒等映射低 256 GiB。
+#[cfg(target_pointer_width = "64")]
+mod paging {
+    pub const SATP_MODE: usize = 8 << 60;
+    pub const MODE_SHIFT: usize = 60;
+    pub const LEAF_SHIFT: usize = 30;
+    pub const LEAVES: usize = 256;
+}
+
+/// Sv32 模式，以 4 MiB 大页恒等映射全部地址空间。
+#[cfg(target_pointer_width = "32")]
+mod paging {
+    pub const SATP_MODE: usize = 1 << 31;
+    pub const MODE_SHIFT: usize = 31;
+    pub const LEAF_SHIFT: usize = 22;
+    pub const LEAVES: usize = 1024;
+}
+
+use paging::*;
+
+const PTES: usize = 4096 / core::mem::size_of::<usize>();
+
+#[repr(C, align(4096))]
+struct PageTable([usize; PTES]);
+
+static mut ROOT: PageTable = PageTable([0; PTES]);
+
+/// Test that SBI calls preserve supervisor CSRs.
+///
+/// `sscratch`, `stvec`, `sepc`, `stval`, `satp` with an identity map and
+/// `senvcfg` are written with known values, and every side-effect free call
+/// is made with interrupts disabled. The values are rewritten before each call,
+/// and read back after it. `satp` is left unchanged and not checked if paging
+/// is already enabled. This test does not run in a thread, as thread
+/// switching writes `stvec` and `sscratch`; the results are recorded and
+/// reported after all CSRs are restored. Current hart `hartid` is used as
+/// parameter of `HSM` status call.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hartid: usize, mut f: impl FnMut(Case)) {
+    f(Case::Begin);
+    let senvcfg = probe_senvcfg();
+    // 已经打开分页的内核可能不在恒等映射的地址上，只写回原值
+    let original_satp = satp::read().bits();
+    let bare = original_satp >> MODE_SHIFT == 0;
+    let root = core::ptr::addr_of_mut!(ROOT);
+    for (i, pte) in unsafe { (*root).0.iter_mut() }.enumerate().take(LEAVES) {
+        // V | R | W | X | A | D
+        *pte = (i << LEAF_SHIFT) >> 12 << 10 | 0xcf;
+    }
+    let values = [
+        0x5a5a_a5a5_5a5a_a5a5_u64 as usize,
+        // 入口可能只按 2 字节对齐，取对齐后的循环
+        (unexpected_trap as usize + 3) & !3,
+        0xa5a5_5a5a_a5a5_5a50_u64 as usize,
+        0x1234_5678_9abc_def0_u64 as usize,
+        if bare {
+            SATP_MODE | root as usize >> 12
+        } else {
+            original_satp
+        },
+        // FIOM | CBIE = 01 | CBCFE | CBZE
+        0xd1,
+    ];
+    let mut records = [None::<(Call, [usize; 6])>; MAX_CALLS];
+    let mut overflow = false;
+    // 关中断后不能有任何陷入，也不能输出
+    let sie = sstatus::read().sie();
+    unsafe { sstatus::clear_sie() };
+    let original = unsafe { read_csrs(senvcfg) };
+    unsafe { write_csrs(&values, senvcfg) };
+    // WARL 字段可能不保存测试值，以读回的值作为期望
+    let expected = unsafe { read_csrs(senvcfg) };
+    for (i, call) in harmless_calls(hartid).enumerate() {
+        if i == MAX_CALLS {
+            overflow = true;
+            break;
+        }
+        unsafe { write_csrs(&expected, senvcfg) };
+        call.invoke();
+        records[i] = Some((call, unsafe { read_csrs(senvcfg) }));
+    }
+    unsafe { write_csrs(&original, senvcfg) };
+    if sie {
+        unsafe { sstatus::set_sie() };
+    }
+    // 报告结果
+    let mut checked = [true; CSRS.len()];
+    checked[SENVCFG] = senvcfg;
+    checked[SATP] = bare && expected[SATP] >> MODE_SHIFT == SATP_MODE >> MODE_SHIFT;
+    for (i, csr) in CSRS.into_iter().enumerate() {
+        if !checked[i] {
+            f(Case::Skipped(csr));
+        }
+    }
+    let mut pass = true;
+    for (call, actual) in records.into_iter().flatten() {
+        let Call { eid, fid, .. } = call;
+        let mut preserved = true;
+        for i in (0..CSRS.len()).filter(|&i| checked[i]) {
+            if actual[i] != expected[i] {
+                f(Case::Clobbered {
+                    eid,
+                    fid,
+                    csr: CSRS[i],
+                    expected: expected[i],
+                    actual: actual[i],
+                });
+                preserved = false;
+            }
+        }
+        if preserved {
+            f(Case::Preserved { eid, fid });
+        }
+        pass &= preserved;
+    }
+    if overflow {
+        f(Case::TooManyCalls);
+        return;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 在线程中读取 `senvcfg`，检查它是否存在。
+fn probe_senvcfg() -> bool {
+    let entry = read_senvcfg as usize;
+    let mut thread = Thread::new(entry);
+    unsafe { thread.execute() };
+    scause::read().cause() == Trap::Exception(Exception::IllegalInstruction)
+        && sepc::read() == entry + 4
+}
+
+/// 读取所有检查的 CSR，不存在的 `senvcfg` 读作 0。
+///
+/// # Safety
+///
+/// 不存在 `senvcfg` 时，`senvcfg` 必须为 `false`。
+#[inline(always)]
+unsafe fn read_csrs(senvcfg: bool) -> [usize; 6] {
+    let mut values = [0; 6];
+    core::arch::asm!(
+        "   csrr {sscratch}, sscratch
+            csrr {stvec},    stvec
+            csrr {sepc},     sepc
+            csrr {stval},    stval
+            csrr {satp},     satp
+        ",
+        sscratch = out(reg) values[0],
+        stvec    = out(reg) values[1],
+        sepc     = out(reg) values[2],
+        stval    = out(reg) values[3],
+        satp     = out(reg) values[SATP],
+    );
+    if senvcfg {
+        core::arch::asm!("csrr {}, 0x10a", out(reg) values[SENVCFG]);
+    }
+    values
+}
+
+/// 写入所有检查的 CSR，然后刷新地址转换缓存。
+///
+/// # Safety
+///
+/// 中断必须已关闭，`satp` 必须是有效的恒等映射或原值。
+/// 不存在 `senvcfg` 时，`senvcfg` 必须为 `false`。
+#[inline(always)]
+unsafe fn write_csrs(values: &[usize; 6], senvcfg: bool) {
+    core::arch::asm!(
+        "   csrw sscratch, {sscratch}
+            csrw stvec,    {stvec}
+            csrw sepc,     {sepc}
+            csrw stval,    {stval}
+            csrw satp,     {satp}
+            sfence.vma
+        ",
+        sscratch = in(reg) values[0],
+        stvec    = in(reg) values[1],
+        sepc     = in(reg) values[2],
+        stval    = in(reg) values[3],
+        satp     = in(reg) values[SATP],
+    );
+    if senvcfg {
+        core::arch::asm!("csrw 0x10a, {}", in(reg) values[SENVCFG]);
+    }
+}
+
+/// 读取 `senvcfg`，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn read_senvcfg() {
+    core::arch::asm!(
+        "   csrr a0, 0x10a
+            unimp
+        ",
+        options(noreturn)
+    )
+}
+
+/// 测试期间 `stvec` 指向的循环，不应发生陷入。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn unexpected_trap() {
+    core::arch::asm!(
+        "   .align 2
+        1:  wfi
+            j    1b
+        ",
+        options(noreturn)
+    )
+}
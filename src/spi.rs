@@ -29,13 +29,28 @@ pub fn test(hart_id: usize, mut f: impl FnMut(Case)) {
         return;
     }
 
+    f(Case::Begin);
+    match trap_ipi(hart_id) {
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            f(Case::SendIpi);
+            f(Case::Pass);
+        }
+        trap => {
+            f(Case::UnexpectedTrap(trap));
+        }
+    }
+}
+
+/// 在线程中向本核 `hart_id` 发送核间中断，返回线程陷入的原因。
+///
+/// 收到核间中断后将清除 `sip.SSIP`，以免影响之后的测试。
+pub(crate) fn trap_ipi(hart_id: usize) -> Trap {
     fn ipi(hart_id: usize) -> ! {
         sbi::send_ipi(sbi_spec::binary::HartMask::from_mask_base(1 << hart_id, 0));
         // 必须立即触发中断，即使是一个指令的延迟，也会触发另一个异常
         unsafe { core::arch::asm!("unimp", options(noreturn, nomem)) };
     }
 
-    f(Case::Begin);
     let mut stack = [0usize; 32];
     let mut thread = Thread::new(ipi as _);
     *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
@@ -44,13 +59,10 @@ pub fn test(hart_id: usize, mut f: impl FnMut(Case)) {
         sie::set_ssoft();
         thread.execute();
     }
-    match scause::read().cause() {
-        Trap::Interrupt(Interrupt::SupervisorSoft) => {
-            f(Case::SendIpi);
-            f(Case::Pass);
-        }
-        trap => {
-            f(Case::UnexpectedTrap(trap));
-        }
+    let trap = scause::read().cause();
+    if trap == Trap::Interrupt(Interrupt::SupervisorSoft) {
+        const SSIP: usize = 1 << 1;
+        unsafe { core::arch::asm!("csrc sip, {}", in(reg) SSIP) };
     }
+    trap
 }
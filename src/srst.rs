@@ -0,0 +1,213 @@
+//! System reset extension test suite.
+
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// System reset extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for system reset extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for a reserved reset type has been rejected.
+    ResetTypeRejected(u32),
+    /// Test failed for invalid parameters are not rejected with expected error.
+    InvalidParamNotRejected {
+        /// The reset type passed to the SBI call.
+        reset_type: u32,
+        /// The reset reason passed to the SBI call.
+        reset_reason: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for marker has been written and system is going to reboot.
+    Reboot(Reboot),
+    /// Test failed for system reboot returned with [`SbiRet`] error.
+    RebootFailed {
+        /// The type of reboot requested.
+        reboot: Reboot,
+        /// The `SbiRet` value for the failed system reset SBI call.
+        ret: SbiRet,
+    },
+    /// Test process for marker written before reboot has been observed on this boot.
+    RebootObserved(Reboot),
+    /// All test cases on system reset extension has passed.
+    Pass,
+}
+
+/// Reboot type to be verified across system reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reboot {
+    /// Warm reboot, power cycles the main processor and parts of the system.
+    Warm,
+    /// Cold reboot, power cycles the entire system.
+    Cold,
+}
+
+/// Shutdown with test verdict process cases.
+#[derive(Clone, Debug)]
+pub enum ShutdownCase {
+    /// Can't report test verdict for system reset extension does not exist.
+    NotExist,
+    /// Process for system is going to shut down with test verdict.
+    Shutdown {
+        /// Whether the verdict to report is success.
+        pass: bool,
+    },
+    /// Process failed for system shutdown returned with [`SbiRet`] error.
+    ShutdownFailed(SbiRet),
+    /// Process for falling back to legacy shutdown, which can't report test verdict.
+    LegacyShutdown,
+    /// Process for no shutdown method is available, current hart is to be parked.
+    Park,
+}
+
+/// 保留的复位类型，合规的实现必须拒绝。
+const RESERVED_RESET_TYPES: [u32; 3] = [0x0000_0003, 0x0000_1000, 0xefff_ffff];
+
+/// Test system reset extension without resetting the system.
+///
+/// Only calls with reserved reset types are issued, so a conforming implementation
+/// must return an error instead of going down. Reserved reset reasons are not
+/// tested, as they can't be checked without a valid reset type and a real reset.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(mut f: impl FnMut(Case)) {
+    use sbi_spec::srst::{RESET_REASON_NO_REASON, RESET_REASON_SYSTEM_FAILURE};
+
+    if sbi::probe_extension(sbi::Reset).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    let mut pass = true;
+    for reset_type in RESERVED_RESET_TYPES {
+        let mut rejected = true;
+        for reset_reason in [RESET_REASON_NO_REASON, RESET_REASON_SYSTEM_FAILURE] {
+            rejected &= check_rejected(reset_type, reset_reason, &mut f);
+        }
+        if rejected {
+            f(Case::ResetTypeRejected(reset_type));
+        }
+        pass &= rejected;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 发起一次必然无效的复位调用，检查其是否被拒绝。
+fn check_rejected(reset_type: u32, reset_reason: u32, mut f: impl FnMut(Case)) -> bool {
+    let ret = sbi::system_reset(reset_type, reset_reason);
+    match ret.err() {
+        Some(Error::InvalidParam | Error::NotSupported) => true,
+        _ => {
+            f(Case::InvalidParamNotRejected {
+                reset_type,
+                reset_reason,
+                ret,
+            });
+            false
+        }
+    }
+}
+
+/// Shut down the system, reporting test verdict `pass` to the host through reset reason.
+///
+/// A failed verdict is reported as `SystemFailure`, so a host like QEMU could
+/// exit with corresponding status. If system reset extension is not available,
+/// legacy shutdown is tried instead, which can't carry the verdict;
+/// if neither is available, current hart is parked.
+///
+/// The process output is to be handled in `f`.
+pub fn shutdown(pass: bool, mut f: impl FnMut(ShutdownCase)) -> ! {
+    use sbi::legacy::LEGACY_SHUTDOWN;
+
+    if sbi::probe_extension(sbi::Reset).is_available() {
+        f(ShutdownCase::Shutdown { pass });
+        let ret = if pass {
+            sbi::system_reset(sbi::Shutdown, sbi::NoReason)
+        } else {
+            sbi::system_reset(sbi::Shutdown, sbi::SystemFailure)
+        };
+        f(ShutdownCase::ShutdownFailed(ret));
+    } else {
+        f(ShutdownCase::NotExist);
+    }
+    if sbi::probe_extension(LEGACY_SHUTDOWN).is_available() {
+        f(ShutdownCase::LegacyShutdown);
+        // 不使用 `sbi::legacy::shutdown`，它在调用返回时会恐慌
+        unsafe { core::arch::asm!("ecall", in("a7") LEGACY_SHUTDOWN, lateout("a0") _) };
+    }
+    f(ShutdownCase::Park);
+    loop {
+        unsafe { riscv::asm::wfi() };
+    }
+}
+
+/// Test system reboot, phase one: write `marker` to `persist`, then reboot the system.
+///
+/// `persist` must be a memory region which survives the reboot, and `marker`
+/// must be a value that would not appear in that region by accident.
+/// On success this function does not return; call [`test_reboot_observed`]
+/// on next boot to finish the test.
+///
+/// The test case output is to be handled in `f`.
+pub fn test_reboot(
+    reboot: Reboot,
+    persist: &mut [usize; 2],
+    marker: usize,
+    mut f: impl FnMut(Case),
+) {
+    use sbi_spec::srst::{RESET_TYPE_COLD_REBOOT, RESET_TYPE_WARM_REBOOT};
+
+    if sbi::probe_extension(sbi::Reset).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    let reset_type = match reboot {
+        Reboot::Warm => RESET_TYPE_WARM_REBOOT,
+        Reboot::Cold => RESET_TYPE_COLD_REBOOT,
+    };
+    // 标记必须在复位前写入内存
+    unsafe {
+        core::ptr::write_volatile(&mut persist[0], marker);
+        core::ptr::write_volatile(&mut persist[1], reset_type as _);
+        core::arch::asm!("fence rw, rw");
+    }
+    f(Case::Reboot(reboot));
+    let ret = sbi::system_reset(reset_type, sbi::NoReason);
+    // 复位失败，清除标记以免下次启动误报
+    unsafe { core::ptr::write_volatile(&mut persist[0], 0) };
+    f(Case::RebootFailed { reboot, ret });
+}
+
+/// Test system reboot, phase two: detect `marker` written by [`test_reboot`] in `persist`.
+///
+/// Returns `true` if the marker is found, which means this boot is caused by the reboot
+/// under test; the marker is cleared then. Returns `false` without any output otherwise.
+///
+/// The test case output is to be handled in `f`.
+pub fn test_reboot_observed(
+    persist: &mut [usize; 2],
+    marker: usize,
+    mut f: impl FnMut(Case),
+) -> bool {
+    use sbi_spec::srst::{RESET_TYPE_COLD_REBOOT, RESET_TYPE_WARM_REBOOT};
+
+    if unsafe { core::ptr::read_volatile(&persist[0]) } != marker {
+        return false;
+    }
+    let reboot = match unsafe { core::ptr::read_volatile(&persist[1]) } as u32 {
+        RESET_TYPE_WARM_REBOOT => Reboot::Warm,
+        RESET_TYPE_COLD_REBOOT => Reboot::Cold,
+        _ => return false,
+    };
+    unsafe { core::ptr::write_volatile(&mut persist[0], 0) };
+    f(Case::Begin);
+    f(Case::RebootObserved(reboot));
+    f(Case::Pass);
+    true
+}
@@ -0,0 +1,454 @@
+//! Supervisor software events extension test suite.
+
+use crate::{
+    binary::{sbi_call_0, sbi_call_1, sbi_call_3, sbi_call_5},
+    thread::Thread,
+};
+use riscv::register::scause::{self, Trap};
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// Supervisor software events extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for supervisor software events extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test failed for reading zero attributes is not rejected with `SBI_ERR_INVALID_PARAM`.
+    AttrCountNotRejected(SbiRet),
+    /// Test failed for unregistering an unused event is not rejected with `SBI_ERR_INVALID_STATE`.
+    UnregisterNotRejected(SbiRet),
+    /// Test failed for misaligned handler entry is not rejected with `SBI_ERR_INVALID_PARAM`.
+    MisalignedNotRejected(SbiRet),
+    /// Test failed for can't register the event with [`SbiRet`] error.
+    RegisterFailed(SbiRet),
+    /// Test failed for registering an event twice is not rejected with `SBI_ERR_INVALID_STATE`.
+    DoubleRegisterNotRejected(SbiRet),
+    /// Test failed for can't read an event attribute with [`SbiRet`] error.
+    ReadAttrsFailed {
+        /// The attribute ID.
+        attr_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for can't write an event attribute with [`SbiRet`] error.
+    WriteAttrsFailed {
+        /// The attribute ID.
+        attr_id: u32,
+        /// The `SbiRet` value for the failed SBI call.
+        ret: SbiRet,
+    },
+    /// Test failed for writing the read-only status attribute is not rejected.
+    ReadOnlyWriteNotRejected(SbiRet),
+    /// Test failed for an event attribute does not hold the expected value.
+    AttrMismatch {
+        /// The attribute ID.
+        attr_id: u32,
+        /// Expected value of the attribute.
+        expected: usize,
+        /// Value read from the attribute.
+        actual: usize,
+    },
+    /// Test failed for can't enable the event with [`SbiRet`] error.
+    EnableFailed(SbiRet),
+    /// Test failed for can't unmask events on current hart with [`SbiRet`] error.
+    UnmaskFailed(SbiRet),
+    /// Test failed for can't inject the event with [`SbiRet`] error.
+    InjectFailed(SbiRet),
+    /// Test failed for unexpected trap occurred upon injecting the event.
+    UnexpectedTrap(Trap),
+    /// Test failed for the handler is not called after the event is injected.
+    HandlerNotCalled,
+    /// Test failed for the handler is called with unexpected `a6` and `a7` register values.
+    HandlerMismatch {
+        /// The `a7` register value on handler entry, expected to be current hart ID.
+        hartid: usize,
+        /// The `a6` register value on handler entry, expected to be the registered argument.
+        arg: usize,
+    },
+    /// Test process for the handler has been called.
+    Handled {
+        /// The `sepc` register value on handler entry, as the interrupted program counter.
+        interrupted_pc: usize,
+    },
+    /// Test failed for `sse_complete` does not return control to the interrupted context.
+    CompleteFailed,
+    /// Test failed for the interrupted `a6` and `a7` registers are not restored.
+    ResumeMismatch {
+        /// The `a6` register value after resumed.
+        a6: usize,
+        /// The `a7` register value after resumed.
+        a7: usize,
+    },
+    /// Test failed for can't disable the event with [`SbiRet`] error.
+    DisableFailed(SbiRet),
+    /// Test failed for can't unregister the event with [`SbiRet`] error.
+    UnregisterFailed(SbiRet),
+    /// All test cases on supervisor software events extension has passed.
+    Pass,
+}
+
+pub(crate) const EID_SSE: usize = 0x535345;
+const READ_ATTRS: usize = 0;
+const WRITE_ATTRS: usize = 1;
+const REGISTER: usize = 2;
+const UNREGISTER: usize = 3;
+const ENABLE: usize = 4;
+const DISABLE: usize = 5;
+const COMPLETE: usize = 6;
+const INJECT: usize = 7;
+const HART_UNMASK: usize = 8;
+const HART_MASK: usize = 9;
+
+/// 本地软件注入事件。
+const LOCAL_SOFTWARE_INJECTED: usize = 0xffff_0000;
+
+const ATTR_STATUS: u32 = 0x00;
+const ATTR_PRIORITY: u32 = 0x01;
+const ATTR_ENTRY_PC: u32 = 0x04;
+const ATTR_ENTRY_ARG: u32 = 0x05;
+
+const STATE_MASK: usize = 0b11;
+const STATE_REGISTERED: usize = 1;
+const STATE_ENABLED: usize = 2;
+
+/// `SBI_ERR_INVALID_STATE`，`sbi-spec` 尚未定义。
+const ERR_INVALID_STATE: Error = Error::Custom(-10);
+
+/// 事件处理函数观察到的参数。
+#[repr(C)]
+struct HandlerRecord {
+    hartid: usize,
+    arg: usize,
+    sepc: usize,
+    count: usize,
+}
+
+static mut RECORD: HandlerRecord = HandlerRecord {
+    hartid: 0,
+    arg: 0,
+    sepc: 0,
+    count: 0,
+};
+
+/// Test supervisor software events extension.
+///
+/// The local software-injected event is registered, enabled and injected
+/// on current hart `hartid`.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(hartid: usize, mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(EID_SSE).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    let record = core::ptr::addr_of_mut!(RECORD);
+    // 检查错误路径
+    let mut buf = [0usize; 2];
+    let ret = read_attrs(ATTR_STATUS, &mut buf[..0]);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::AttrCountNotRejected(ret));
+        return;
+    }
+    let ret = sbi_call_1(EID_SSE, UNREGISTER, LOCAL_SOFTWARE_INJECTED);
+    if ret.err() != Some(ERR_INVALID_STATE) {
+        f(Case::UnregisterNotRejected(ret));
+        return;
+    }
+    let ret = register(handler_entry as usize + 1, record as _);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::MisalignedNotRejected(ret));
+        return;
+    }
+    // 注册事件
+    let ret = register(handler_entry as _, record as _);
+    if ret.is_err() {
+        f(Case::RegisterFailed(ret));
+        return;
+    }
+    let pass = test_registered(record, &mut f) && test_inject(hartid, record, &mut f);
+    // 无论成败都注销事件
+    let ret = sbi_call_1(EID_SSE, DISABLE, LOCAL_SOFTWARE_INJECTED);
+    if pass && ret.is_err() && ret.err() != Some(ERR_INVALID_STATE) {
+        f(Case::DisableFailed(ret));
+        return;
+    }
+    let ret = sbi_call_1(EID_SSE, UNREGISTER, LOCAL_SOFTWARE_INJECTED);
+    if pass && ret.is_err() {
+        f(Case::UnregisterFailed(ret));
+        return;
+    }
+    if pass {
+        f(Case::Pass);
+    }
+}
+
+/// 测试已注册事件的属性读写，以及重复注册的错误路径。
+fn test_registered(record: *mut HandlerRecord, mut f: impl FnMut(Case)) -> bool {
+    let ret = register(handler_entry as _, record as _);
+    if ret.err() != Some(ERR_INVALID_STATE) {
+        f(Case::DoubleRegisterNotRejected(ret));
+        return false;
+    }
+    // 入口地址和参数应与注册时一致
+    let mut buf = [0usize; 2];
+    let ret = read_attrs(ATTR_ENTRY_PC, &mut buf);
+    if ret.is_err() {
+        f(Case::ReadAttrsFailed {
+            attr_id: ATTR_ENTRY_PC,
+            ret,
+        });
+        return false;
+    }
+    for (attr_id, expected, actual) in [
+        (ATTR_ENTRY_PC, handler_entry as usize, buf[0]),
+        (ATTR_ENTRY_ARG, record as usize, buf[1]),
+    ] {
+        if actual != expected {
+            f(Case::AttrMismatch {
+                attr_id,
+                expected,
+                actual,
+            });
+            return false;
+        }
+    }
+    if !check_state(STATE_REGISTERED, &mut f) {
+        return false;
+    }
+    // 状态属性只读
+    let ret = write_attrs(ATTR_STATUS, &[STATE_REGISTERED]);
+    if ret.is_ok() {
+        f(Case::ReadOnlyWriteNotRejected(ret));
+        return false;
+    }
+    // 修改优先级并读回，然后恢复原值
+    let mut original = [0usize];
+    let ret = read_attrs(ATTR_PRIORITY, &mut original);
+    if ret.is_err() {
+        f(Case::ReadAttrsFailed {
+            attr_id: ATTR_PRIORITY,
+            ret,
+        });
+        return false;
+    }
+    let priority = if original[0] == 1 { 2 } else { 1 };
+    let ret = write_attrs(ATTR_PRIORITY, &[priority]);
+    if ret.is_err() {
+        f(Case::WriteAttrsFailed {
+            attr_id: ATTR_PRIORITY,
+            ret,
+        });
+        return false;
+    }
+    let mut actual = [0usize];
+    let ret = read_attrs(ATTR_PRIORITY, &mut actual);
+    if ret.is_err() {
+        f(Case::ReadAttrsFailed {
+            attr_id: ATTR_PRIORITY,
+            ret,
+        });
+        return false;
+    }
+    if actual[0] != priority {
+        f(Case::AttrMismatch {
+            attr_id: ATTR_PRIORITY,
+            expected: priority,
+            actual: actual[0],
+        });
+        return false;
+    }
+    let ret = write_attrs(ATTR_PRIORITY, &original);
+    if ret.is_err() {
+        f(Case::WriteAttrsFailed {
+            attr_id: ATTR_PRIORITY,
+            ret,
+        });
+        return false;
+    }
+    true
+}
+
+/// 使能事件，在线程中注入事件，检查处理函数和被打断的上下文。
+fn test_inject(hartid: usize, record: *mut HandlerRecord, mut f: impl FnMut(Case)) -> bool {
+    use riscv::register::scause::Exception;
+
+    let ret = sbi_call_1(EID_SSE, ENABLE, LOCAL_SOFTWARE_INJECTED);
+    if ret.is_err() {
+        f(Case::EnableFailed(ret));
+        return false;
+    }
+    if !check_state(STATE_ENABLED, &mut f) {
+        return false;
+    }
+    // 事件在启动时默认屏蔽
+    let unmask = sbi_call_0(EID_SSE, HART_UNMASK);
+    if unmask.is_err() && unmask.err() != Some(Error::AlreadyStarted) {
+        f(Case::UnmaskFailed(unmask));
+        return false;
+    }
+    // 在线程中注入事件，由线程上下文捕获被打断的状态
+    let mut thread = Thread::new(inject as _);
+    *thread.a_mut(0) = LOCAL_SOFTWARE_INJECTED;
+    *thread.a_mut(1) = hartid;
+    *thread.a_mut(6) = INJECT;
+    *thread.a_mut(7) = EID_SSE;
+    unsafe {
+        (*record).count = 0;
+        thread.execute();
+    }
+    if unmask.is_ok() {
+        let _ = sbi_call_0(EID_SSE, HART_MASK);
+    }
+    let trap = scause::read().cause();
+    if trap != Trap::Exception(Exception::IllegalInstruction) {
+        f(Case::UnexpectedTrap(trap));
+        return false;
+    }
+    let ret = SbiRet {
+        error: thread.a(0),
+        value: thread.a(1),
+    };
+    if ret.is_err() {
+        f(Case::InjectFailed(ret));
+        return false;
+    }
+    let record = unsafe { &*record };
+    if record.count == 0 {
+        f(Case::HandlerNotCalled);
+        return false;
+    }
+    if record.hartid != hartid || record.arg != record as *const _ as usize {
+        f(Case::HandlerMismatch {
+            hartid: record.hartid,
+            arg: record.arg,
+        });
+        return false;
+    }
+    f(Case::Handled {
+        interrupted_pc: record.sepc,
+    });
+    // 处理函数中完成事件失败时会在处理函数中陷入，不会执行到标记
+    if thread.a(5) != 1 {
+        f(Case::CompleteFailed);
+        return false;
+    }
+    if thread.a(6) != INJECT || thread.a(7) != EID_SSE {
+        f(Case::ResumeMismatch {
+            a6: thread.a(6),
+            a7: thread.a(7),
+        });
+        return false;
+    }
+    true
+}
+
+/// 检查事件状态属性中的状态位。
+fn check_state(expected: usize, mut f: impl FnMut(Case)) -> bool {
+    let mut status = [0usize];
+    let ret = read_attrs(ATTR_STATUS, &mut status);
+    if ret.is_err() {
+        f(Case::ReadAttrsFailed {
+            attr_id: ATTR_STATUS,
+            ret,
+        });
+        return false;
+    }
+    if status[0] & STATE_MASK != expected {
+        f(Case::AttrMismatch {
+            attr_id: ATTR_STATUS,
+            expected,
+            actual: status[0] & STATE_MASK,
+        });
+        return false;
+    }
+    true
+}
+
+#[inline]
+fn register(entry_pc: usize, entry_arg: usize) -> SbiRet {
+    sbi_call_3(
+        EID_SSE,
+        REGISTER,
+        LOCAL_SOFTWARE_INJECTED,
+        entry_pc,
+        entry_arg,
+    )
+}
+
+#[inline]
+fn read_attrs(base_attr_id: u32, output: &mut [usize]) -> SbiRet {
+    sbi_call_5(
+        EID_SSE,
+        READ_ATTRS,
+        LOCAL_SOFTWARE_INJECTED,
+        base_attr_id as _,
+        output.len(),
+        output.as_mut_ptr() as _,
+        0,
+    )
+}
+
+#[inline]
+fn write_attrs(base_attr_id: u32, input: &[usize]) -> SbiRet {
+    sbi_call_5(
+        EID_SSE,
+        WRITE_ATTRS,
+        LOCAL_SOFTWARE_INJECTED,
+        base_attr_id as _,
+        input.len(),
+        input.as_ptr() as _,
+        0,
+    )
+}
+
+/// 在线程中注入事件。
+///
+/// 事件处理完成后回到 `ecall` 之后，设置 `a5` 作为标记，然后以非法指令陷入调度。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn inject() {
+    core::arch::asm!(
+        "   ecall
+            li   a5, 1
+            unimp
+        ",
+        options(noreturn)
+    )
+}
+
+/// 事件处理入口，`a6` 为注册时的参数，`a7` 为当前硬件线程号。
+///
+/// 只使用由 SBI 保存的 `a6` 和 `a7`，记录参数后完成事件。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn handler_entry() {
+    core::arch::asm!(
+        // 记录参数
+        "   sd   a7, 0*8(a6)
+            sd   a6, 1*8(a6)
+            csrr a7, sepc
+            sd   a7, 2*8(a6)
+            ld   a7, 3*8(a6)
+            addi a7, a7, 1
+            sd   a7, 3*8(a6)
+        ",
+        // 完成事件，不应返回
+        "   li   a7, {eid}
+            li   a6, {fid}
+            ecall
+            unimp
+        ",
+        eid = const EID_SSE,
+        fid = const COMPLETE,
+        options(noreturn)
+    )
+}
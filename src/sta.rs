@@ -0,0 +1,191 @@
+//! Steal-time accounting extension test suite.
+
+use riscv::register::scause::Trap;
+use sbi::{SbiRet, SharedPtr};
+use sbi_spec::{binary::Error, sta::EID_STA};
+
+/// Steal-time accounting extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for steal-time accounting extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test failed for misaligned shared memory is not rejected with `SBI_ERR_INVALID_PARAM`.
+    MisalignedNotRejected(SbiRet),
+    /// Test failed for can't set shared memory with [`SbiRet`] error.
+    SetShmemFailed(SbiRet),
+    /// Test failed for sequence counter stays odd, as if the record is never finished updating.
+    SequenceOdd(u32),
+    /// Test process for a consistent steal-time record has been read.
+    StealTime {
+        /// The sequence counter of the record.
+        sequence: u32,
+        /// Amount of time in nanoseconds that current virtual hart has been idle.
+        steal: u64,
+        /// Whether current virtual hart has been preempted.
+        preempted: bool,
+    },
+    /// Test failed for sequence counter does not advance over time.
+    SequenceNotAdvanced(u32),
+    /// Test failed for steal time has decreased during period of two reads.
+    StealDecreased {
+        /// The steal time for the first read.
+        a: u64,
+        /// The steal time for the second read.
+        b: u64,
+    },
+    /// Test failed for unexpected trap occurred upon waiting for time to pass.
+    UnexpectedTrap(Trap),
+    /// Test failed for steal-time record is still updated after shared memory is disabled.
+    UpdatedAfterDisable,
+    /// All test cases on steal-time accounting extension has passed.
+    Pass,
+}
+
+/// 窃取时间记录。
+#[repr(C, align(64))]
+struct StealTimeRecord {
+    sequence: u32,
+    flags: u32,
+    steal: u64,
+    preempted: u8,
+    pad: [u8; 47],
+}
+
+static mut RECORD: StealTimeRecord = StealTimeRecord {
+    sequence: 0,
+    flags: 0,
+    steal: 0,
+    preempted: 0,
+    pad: [0; 47],
+};
+
+/// 等待序列号变为偶数的最大重试次数。
+const MAX_RETRY: usize = 1_000_000;
+
+/// Test steal-time accounting extension.
+///
+/// Current hart is idled by waiting for a timer of `delay` twice, expecting
+/// the sequence counter to advance.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(delay: u64, mut f: impl FnMut(Case)) {
+    use crate::time::trap_timer;
+    use riscv::register::scause::Interrupt;
+
+    if sbi::probe_extension(EID_STA).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    let record = core::ptr::addr_of_mut!(RECORD);
+    // 检查错误路径
+    let ret = sbi::sta_set_shmem(SharedPtr::new(record as usize + 8, 0), 0);
+    if ret.err() != Some(Error::InvalidParam) {
+        f(Case::MisalignedNotRejected(ret));
+        return;
+    }
+    // 设置共享内存
+    let ret = sbi::sta_set_shmem(SharedPtr::new(record as _, 0), 0);
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let disable = || sbi::sta_set_shmem(SharedPtr::new(usize::MAX, usize::MAX), 0);
+    let (sequence, steal) = match read(record) {
+        Ok((sequence, steal, preempted)) => {
+            f(Case::StealTime {
+                sequence,
+                steal,
+                preempted,
+            });
+            (sequence, steal)
+        }
+        Err(sequence) => {
+            f(Case::SequenceOdd(sequence));
+            let _ = disable();
+            return;
+        }
+    };
+    // 等待一段时间，让出当前虚拟核
+    let trap = trap_timer(delay);
+    sbi::set_timer(u64::MAX);
+    if trap != Trap::Interrupt(Interrupt::SupervisorTimer) {
+        f(Case::UnexpectedTrap(trap));
+        let _ = disable();
+        return;
+    }
+    let (sequence_later, steal_later) = match read(record) {
+        Ok((sequence, steal, preempted)) => {
+            f(Case::StealTime {
+                sequence,
+                steal,
+                preempted,
+            });
+            (sequence, steal)
+        }
+        Err(sequence) => {
+            f(Case::SequenceOdd(sequence));
+            let _ = disable();
+            return;
+        }
+    };
+    if sequence_later == sequence {
+        f(Case::SequenceNotAdvanced(sequence));
+        let _ = disable();
+        return;
+    }
+    if steal_later < steal {
+        f(Case::StealDecreased {
+            a: steal,
+            b: steal_later,
+        });
+        let _ = disable();
+        return;
+    }
+    // 关闭共享内存，此后记录不应再更新
+    let ret = disable();
+    if ret.is_err() {
+        f(Case::SetShmemFailed(ret));
+        return;
+    }
+    let sequence = unsafe { core::ptr::addr_of!((*record).sequence).read_volatile() };
+    let trap = trap_timer(delay);
+    sbi::set_timer(u64::MAX);
+    if trap != Trap::Interrupt(Interrupt::SupervisorTimer) {
+        f(Case::UnexpectedTrap(trap));
+        return;
+    }
+    if unsafe { core::ptr::addr_of!((*record).sequence).read_volatile() } != sequence {
+        f(Case::UpdatedAfterDisable);
+        return;
+    }
+    f(Case::Pass);
+}
+
+/// 按序列号协议一致地读取记录，返回序列号、窃取时间和是否被抢占。
+///
+/// 序列号一直为奇数时返回最后读到的序列号。
+fn read(record: *const StealTimeRecord) -> Result<(u32, u64, bool), u32> {
+    use core::ptr::addr_of;
+
+    let mut sequence = 0;
+    for _ in 0..MAX_RETRY {
+        unsafe {
+            sequence = addr_of!((*record).sequence).read_volatile();
+            if sequence % 2 != 0 {
+                core::hint::spin_loop();
+                continue;
+            }
+            core::arch::asm!("fence r, r");
+            let steal = addr_of!((*record).steal).read_volatile();
+            let preempted = addr_of!((*record).preempted).read_volatile() != 0;
+            core::arch::asm!("fence r, r");
+            if addr_of!((*record).sequence).read_volatile() == sequence {
+                return Ok((sequence, steal, preempted));
+            }
+        }
+    }
+    Err(sequence)
+}
@@ -0,0 +1,254 @@
+//! System suspend extension test suite.
+
+use sbi::SbiRet;
+use sbi_spec::{
+    binary::Error,
+    hsm::hart_state,
+    susp::{EID_SUSP, SUSPEND},
+};
+
+/// System suspend extension test cases.
+#[derive(Clone, Debug)]
+pub enum Case {
+    /// Can't procceed test for system suspend extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for a reserved sleep type has been rejected.
+    SleepTypeRejected(u32),
+    /// Test failed for a reserved sleep type is not rejected with `SBI_ERR_INVALID_PARAM`.
+    SleepTypeNotRejected {
+        /// The reserved sleep type passed to the SBI call.
+        sleep_type: u32,
+        /// The `SbiRet` value for the SBI call.
+        ret: SbiRet,
+    },
+    /// Test is stopped before real suspend for hart state monitor extension does not exist,
+    /// so whether other harts are stopped can't be checked.
+    HsmNotExist,
+    /// Test is stopped before real suspend for a hart other than current one is not stopped.
+    HartNotStopped(usize),
+    /// Test failed for suspend to RAM returned with [`SbiRet`] error.
+    SuspendFailed(SbiRet),
+    /// Test process for system has resumed from suspend to RAM.
+    Resumed,
+    /// Test failed for resumed with unexpected `a0` and `a1` register values.
+    ResumeMismatch {
+        /// The `a0` register value on resume, expected to be current hart ID.
+        hartid: usize,
+        /// The `a1` register value on resume, expected to be the opaque value passed.
+        opaque: usize,
+    },
+    /// All test cases on system suspend extension has passed.
+    Pass,
+}
+
+/// 保留的休眠类型，合规的实现必须拒绝。
+const RESERVED_SLEEP_TYPES: [u32; 2] = [0x0000_0001, 0x7fff_ffff];
+/// 休眠到内存。
+const SLEEP_TYPE_SUSPEND_TO_RAM: u32 = 0;
+
+/// Test system suspend extension.
+///
+/// Current hart `hartid` suspends the system to RAM, if all other harts
+/// in `hart_mask` based on `hart_mask_base` are stopped. A timer of `delay`
+/// is set as wake up event before suspending.
+///
+/// The test case output is to be handled in `f`.
+pub fn test(
+    hartid: usize,
+    mut hart_mask: usize,
+    hart_mask_base: usize,
+    delay: u64,
+    mut f: impl FnMut(Case),
+) {
+    use riscv::register::{sie, time};
+
+    // sbi-rt 中 `Suspend` 的扩展号有误，直接使用扩展号探测
+    if sbi::probe_extension(EID_SUSP).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+    f(Case::Begin);
+    // 测试保留的休眠类型；不合规的实现可能真的休眠，因此同样设置唤醒事件和恢复上下文
+    unsafe { sie::set_stimer() };
+    for sleep_type in RESERVED_SLEEP_TYPES {
+        let mut ctx = SuspendContext::ZERO;
+        sbi::set_timer(time::read64() + delay);
+        let ret = unsafe { suspend(&mut ctx, sleep_type as _) };
+        sbi::set_timer(u64::MAX);
+        if ctx.resumed == 0 && ret.err() == Some(Error::InvalidParam) {
+            f(Case::SleepTypeRejected(sleep_type));
+        } else {
+            f(Case::SleepTypeNotRejected { sleep_type, ret });
+            return;
+        }
+    }
+    // 休眠前其他核必须全部停止
+    if sbi::probe_extension(sbi::Hsm).is_unavailable() {
+        f(Case::HsmNotExist);
+        return;
+    }
+    let mut id = hart_mask_base;
+    while hart_mask != 0 {
+        if id != hartid && sbi::hart_get_status(id) != STOPPED {
+            f(Case::HartNotStopped(id));
+            return;
+        }
+        let distance = hart_mask.trailing_zeros() + 1;
+        hart_mask >>= distance;
+        id += distance as usize;
+    }
+    // 休眠到内存，以定时器作为唤醒事件
+    let mut ctx = SuspendContext::ZERO;
+    sbi::set_timer(time::read64() + delay);
+    let ret = unsafe { suspend(&mut ctx, SLEEP_TYPE_SUSPEND_TO_RAM as _) };
+    sbi::set_timer(u64::MAX);
+    if ctx.resumed == 0 {
+        f(Case::SuspendFailed(ret));
+        return;
+    }
+    f(Case::Resumed);
+    if ctx.hartid != hartid || ctx.opaque != &ctx as *const _ as usize {
+        f(Case::ResumeMismatch {
+            hartid: ctx.hartid,
+            opaque: ctx.opaque,
+        });
+        return;
+    }
+    f(Case::Pass);
+}
+
+const STOPPED: SbiRet = SbiRet::success(hart_state::STOPPED);
+
+/// 休眠前保存的调度上下文，以及恢复时观察到的参数。
+#[repr(C)]
+struct SuspendContext {
+    /// `ra`、`sp`、`gp`、`tp` 和 `s0`~`s11`。
+    regs: [usize; 16],
+    /// `stvec`、`sscratch`、`sie`、`sstatus` 和 `satp`，不可恢复休眠后全部丢失。
+    csrs: [usize; 5],
+    hartid: usize,
+    opaque: usize,
+    resumed: usize,
+}
+
+impl SuspendContext {
+    const ZERO: Self = Self {
+        regs: [0; 16],
+        csrs: [0; 5],
+        hartid: 0,
+        opaque: 0,
+        resumed: 0,
+    };
+}
+
+/// 保存调度上下文，然后以 `sleep_type` 休眠。
+///
+/// 休眠失败时返回 SBI 调用的结果；恢复时从 [`resume_entry`] 返回成功。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn suspend(ctx: *mut SuspendContext, sleep_type: usize) -> SbiRet {
+    core::arch::asm!(
+        // 保存调度上下文
+        "   sd   ra,  0*8(a0)
+            sd   sp,  1*8(a0)
+            sd   gp,  2*8(a0)
+            sd   tp,  3*8(a0)
+            sd   s0,  4*8(a0)
+            sd   s1,  5*8(a0)
+            sd   s2,  6*8(a0)
+            sd   s3,  7*8(a0)
+            sd   s4,  8*8(a0)
+            sd   s5,  9*8(a0)
+            sd   s6, 10*8(a0)
+            sd   s7, 11*8(a0)
+            sd   s8, 12*8(a0)
+            sd   s9, 13*8(a0)
+            sd  s10, 14*8(a0)
+            sd  s11, 15*8(a0)
+        ",
+        // 保存 S 态 CSR
+        "   csrr t0, stvec
+            sd   t0, 16*8(a0)
+            csrr t0, sscratch
+            sd   t0, 17*8(a0)
+            csrr t0, sie
+            sd   t0, 18*8(a0)
+            csrr t0, sstatus
+            sd   t0, 19*8(a0)
+            csrr t0, satp
+            sd   t0, 20*8(a0)
+        ",
+        // 休眠，上下文地址作为不透明参数
+        "   mv   a2, a0
+            mv   a0, a1
+            la   a1, {resume_entry}
+            li   a6, {fid}
+            li   a7, {eid}
+            ecall
+            ret
+        ",
+        resume_entry = sym resume_entry,
+        fid          = const SUSPEND,
+        eid          = const EID_SUSP,
+        options(noreturn)
+    )
+}
+
+/// 休眠恢复入口，记录 `a0` 和 `a1`，然后恢复 S 态 CSR 和调度上下文并返回成功。
+///
+/// # Safety
+///
+/// 裸函数。
+#[naked]
+unsafe extern "C" fn resume_entry(hartid: usize, opaque: *mut SuspendContext) -> SbiRet {
+    core::arch::asm!(
+        // 记录恢复参数
+        "   sd   a0, 21*8(a1)
+            sd   a1, 22*8(a1)
+            li   t0, 1
+            sd   t0, 23*8(a1)
+        ",
+        // 恢复 S 态 CSR，先恢复地址转换
+        "   ld   t0, 20*8(a1)
+            csrw satp, t0
+            sfence.vma
+            ld   t0, 16*8(a1)
+            csrw stvec, t0
+            ld   t0, 17*8(a1)
+            csrw sscratch, t0
+            ld   t0, 18*8(a1)
+            csrw sie, t0
+            ld   t0, 19*8(a1)
+            csrw sstatus, t0
+        ",
+        // 恢复调度上下文
+        "   ld   ra,  0*8(a1)
+            ld   sp,  1*8(a1)
+            ld   gp,  2*8(a1)
+            ld   tp,  3*8(a1)
+            ld   s0,  4*8(a1)
+            ld   s1,  5*8(a1)
+            ld   s2,  6*8(a1)
+            ld   s3,  7*8(a1)
+            ld   s4,  8*8(a1)
+            ld   s5,  9*8(a1)
+            ld   s6, 10*8(a1)
+            ld   s7, 11*8(a1)
+            ld   s8, 12*8(a1)
+            ld   s9, 13*8(a1)
+            ld  s10, 14*8(a1)
+            ld  s11, 15*8(a1)
+        ",
+        // 返回成功
+        "   li   a0, 0
+            li   a1, 0
+            ret
+        ",
+        options(noreturn)
+    )
+}
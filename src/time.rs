@@ -36,7 +36,7 @@ pub enum Case {
 
 /// Test timer extension.
 pub fn test(delay: u64, mut f: impl FnMut(Case)) {
-    use riscv::register::{scause::Interrupt, sie, time};
+    use riscv::register::scause::Interrupt;
 
     if sbi::probe_extension(sbi::Timer).is_unavailable() {
         f(Case::NotExist);
@@ -72,16 +72,7 @@ pub fn test(delay: u64, mut f: impl FnMut(Case)) {
     }
     f(Case::Interval { begin, end });
 
-    let mut stack = [0usize; 32];
-    let mut thread = Thread::new(riscv::asm::wfi as _);
-    *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
-
-    sbi::set_timer(time::read64() + delay);
-    unsafe {
-        sie::set_stimer();
-        thread.execute();
-    }
-    match scause::read().cause() {
+    match trap_timer(delay) {
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             sbi::set_timer(u64::MAX);
             f(Case::SetTimer);
@@ -92,3 +83,26 @@ pub fn test(delay: u64, mut f: impl FnMut(Case)) {
         }
     }
 }
+
+/// 设置 `delay` 之后的定时器，在线程中等待中断，返回线程陷入的原因。
+///
+/// 定时器中断到来后不会清除，由调用者重新设置定时器。
+pub(crate) fn trap_timer(delay: u64) -> Trap {
+    sbi::set_timer(riscv::register::time::read64() + delay);
+    wait_timer()
+}
+
+/// 打开定时器中断，在线程中等待中断，返回线程陷入的原因。
+pub(crate) fn wait_timer() -> Trap {
+    use riscv::register::sie;
+
+    let mut stack = [0usize; 32];
+    let mut thread = Thread::new(riscv::asm::wfi as _);
+    *thread.sp_mut() = stack.as_mut_ptr_range().end as _;
+
+    unsafe {
+        sie::set_stimer();
+        thread.execute();
+    }
+    scause::read().cause()
+}